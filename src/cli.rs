@@ -16,6 +16,7 @@ pub struct GenerateOptions {
     #[arg(short, long)]
     pub from: Option<String>,
 
+    /// Replace the output directory instead of merging the generated files into it
     #[arg(long, default_value_t = false)]
     pub force: bool,
 
@@ -39,8 +40,21 @@ impl Display for BuildSystem {
 #[derive(Debug, Subcommand)]
 pub enum Command {
     New {
-        out: PathBuf,
+        #[arg(required_unless_present = "archive")]
+        out: Option<PathBuf>,
+
+        /// Write the project into a .tar, .tar.gz/.tgz or .zip archive instead of a directory
+        #[arg(long, conflicts_with = "out")]
+        archive: Option<PathBuf>,
     },
 
     Init { },
+
+    /// Check a project description for errors
+    Validate {
+        description: String,
+    },
+
+    /// Print the JSON Schema of the project description format
+    Schema,
 }
@@ -0,0 +1,91 @@
+use std::{fmt, io, path::PathBuf};
+
+use crate::{project::ParseError, tree::PathError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Project description not found at `{}`", .0.display())]
+    DescriptionNotFound(PathBuf),
+
+    #[error("More than one description file found in `{}`", .path.display())]
+    AmbiguousDescription {
+        path: PathBuf,
+        candidates: Vec<PathBuf>,
+    },
+
+    #[error("Unsupported project description format: `{}`", .0.display())]
+    UnsupportedFormat(PathBuf),
+
+    #[error("Cannot read project description `{}`: {source}", .path.display())]
+    ReadDescription { path: PathBuf, source: io::Error },
+
+    #[error("Cannot parse project description `{}`: {source}", .path.display())]
+    ParseDescription { path: PathBuf, source: ParseError },
+
+    #[error("`{}` is not a valid project description", .0.display())]
+    InvalidDescription(PathBuf),
+
+    #[error("Cannot read `{}` for `{entry}`: {source}", .path.display())]
+    SourceFile {
+        entry: String,
+        path: PathBuf,
+        source: io::Error,
+    },
+
+    #[error("Invalid glob pattern `{pattern}` for `{entry}`: {source}")]
+    InvalidPattern {
+        entry: String,
+        pattern: String,
+        source: glob::PatternError,
+    },
+
+    #[error("Libraries depend on each other in a cycle: {}", .0.join(" -> "))]
+    DependencyCycle(Vec<String>),
+
+    #[error("Invalid base64 contents for `{entry}`: {source}")]
+    InvalidBase64 {
+        entry: String,
+        source: base64::DecodeError,
+    },
+
+    #[error("Header-only library `{library}` cannot have compiled source `{file}`")]
+    InterfaceSource { library: String, file: String },
+
+    #[error(
+        "Installed library `{library}` cannot link package `{package}`: packages are not installed, \
+         so only shared libraries may link them, and only privately"
+    )]
+    ExportedPackage { library: String, package: String },
+
+    #[error("Library `{0}` is reserved for the project library in the install export")]
+    ReservedLibraryName(String),
+
+    #[error("Version `{0}` must be up to four dot-separated numbers, e.g. `1.2.0`")]
+    InvalidVersion(String),
+
+    #[error("Option `{0}` is not a valid macro name")]
+    InvalidOptionName(String),
+
+    #[error("License file `{0}` is not generated, add it to `files`")]
+    MissingLicenseFile(String),
+
+    #[error("`{entry}` is a symlink and cannot have a mode")]
+    SymlinkMode { entry: String },
+
+    #[error("`{entry}` must be a single file")]
+    NotASingleFile { entry: String },
+
+    #[error(transparent)]
+    UnsafePath(#[from] PathError),
+
+    #[error("Cannot format generated file: {0}")]
+    Format(#[from] fmt::Error),
+
+    #[error("Unsupported archive format: `{}`", .0.display())]
+    UnsupportedArchive(PathBuf),
+
+    #[error("Cannot write `{}`: {source}", .path.display())]
+    Write { path: PathBuf, source: io::Error },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -1,16 +1,22 @@
 use std::path::Path;
 
-use crate::{project::Project, tree::SourceTree};
+use crate::{Error, Result, project::Project, sink::Sink, tree::SourceTree};
 
 mod cmake;
 
 pub use cmake::CmakeProject;
 
-pub trait Generator: TryFrom<Project, Error=anyhow::Error> {
-    fn into_tree(self) -> anyhow::Result<SourceTree>;
+pub trait Generator: TryFrom<Project, Error = Error> {
+    fn into_tree(self) -> Result<SourceTree>;
 
-    fn generate(project: Project, out: &Path) -> anyhow::Result<()> {
-        Self::try_from(project)?.into_tree()?.render(out)?;
+    fn generate(project: Project, out: &Path, replace: bool) -> Result<()> {
+        Self::try_from(project)?.into_tree()?.render(out, replace)?;
+
+        Ok(())
+    }
+
+    fn generate_into(project: Project, sink: &mut dyn Sink) -> Result<()> {
+        Self::try_from(project)?.into_tree()?.write(sink)?;
 
         Ok(())
     }
@@ -1,16 +1,26 @@
+use file::SourceFile;
 use libs::CmakeLibs;
 use main::CmakeMain;
 use src::CmakeSrc;
 use testing::Testing;
 
-use crate::{project::Project, tree::SourceTree};
+use crate::{
+    Error, Result,
+    project::Project,
+    tree::{SourceTree, normalize_name},
+};
 
 use super::Generator;
 
+mod config_header;
 mod libs;
+mod library;
 mod main;
 mod src;
 mod file;
+mod install;
+mod packaging;
+mod pkg_config;
 mod testing;
 
 pub struct CmakeProject {
@@ -18,36 +28,238 @@ pub struct CmakeProject {
     src: CmakeSrc,
     libs: Option<CmakeLibs>,
     testing: Option<Testing>,
+    files: Vec<SourceFile>,
 }
 
 impl TryFrom<Project> for CmakeProject {
-    type Error = anyhow::Error;
+    type Error = Error;
+
+    fn try_from(value: Project) -> Result<Self> {
+        let src = CmakeSrc::try_from(&value)?;
+        let testing = Testing::from_project(&value, src.library())?;
+
+        let mut main = CmakeMain::try_from(&value)?;
+        let mut compiled = src
+            .files
+            .iter()
+            .chain(src.libraries.iter().flat_map(|l| &l.files))
+            .chain(testing.iter().flat_map(|t| &t.files));
+        if compiled.any(|f| f.name.ends_with(".c")) {
+            main.languages.insert(0, "C");
+        }
 
-    fn try_from(value: Project) -> Result<Self, Self::Error> {
         Ok(Self {
-            main: CmakeMain::try_from(&value)?,
-            src: CmakeSrc::try_from(&value)?,
+            main,
+            src,
             libs: CmakeLibs::from_project(&value)?,
-            testing: Testing::from_project(&value)?,
+            testing,
+            files: load_files(&value)?,
         })
     }
 }
 
 impl Generator for CmakeProject {
-    fn into_tree(self) -> anyhow::Result<SourceTree> {
-        let mut children = vec![
-            self.main.try_into()?,
-            self.src.try_into()?,
-        ];
+    fn into_tree(mut self) -> Result<SourceTree> {
+        let public_headers = std::mem::take(&mut self.src.public_headers);
+        let assets = std::mem::take(&mut self.src.assets);
+        let config = self.main.install.as_ref().map(|i| i.config_node()).transpose()?;
+        let config_header = self.main.config_header.as_ref().map(|c| c.node()).transpose()?;
+        let license_file = self.main.packaging.as_ref().and_then(|p| p.license_file.clone());
+        let mut tree = SourceTree::default();
+        tree.insert(self.main.try_into()?);
+        for node in config.into_iter().chain(config_header) {
+            tree.insert(node);
+        }
+        tree.insert(self.src.try_into()?);
 
         if let Some(libs) = self.libs {
-            children.push(libs.into_node()?);
+            tree.insert(libs.into_node()?);
         }
 
         if let Some(testing) = self.testing {
-            children.push(testing.into_node()?);
+            tree.insert(testing.into_node()?);
+        }
+
+        for file in public_headers.into_iter().chain(assets).chain(self.files) {
+            file.insert_into(&mut tree.children);
+        }
+
+        // CPack fails when the license file is missing from the generated project.
+        if let Some(license_file) = license_file
+            && !tree.contains_file(&normalize_name(&license_file)?)
+        {
+            return Err(Error::MissingLicenseFile(license_file));
+        }
+
+        Ok(tree)
+    }
+}
+
+/// Loads the extra files placed relative to the project root.
+fn load_files(project: &Project) -> Result<Vec<SourceFile>> {
+    let mut files = Vec::new();
+    for (name, file) in &project.files {
+        if file.mode.is_some() && matches!(file.source, crate::project::SourceFile::Symlink(_)) {
+            return Err(Error::SymlinkMode { entry: name.clone() });
+        }
+        for mut loaded in SourceFile::load(name.clone(), &file.source, project)? {
+            loaded.mode = file.mode.map(|m| m.0);
+            files.push(loaded);
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::project::Format;
+    use crate::tree::{PathError, SourceNode};
+
+    use super::*;
+
+    const DESCRIPTION: &str = indoc! {r#"
+        name = "ordered"
+
+        [testing.sources]
+        "test_z.cpp".contents = ""
+        "test_a.cpp".contents = ""
+        "test_m.cpp".contents = ""
+
+        [src.sources]
+        "z.cpp".contents = ""
+        "a.cpp".contents = ""
+        "m.cpp".contents = ""
+        "b.cpp".contents = ""
+        "y.cpp".contents = ""
+
+        [src.headers]
+        "z.hpp".contents = ""
+        "a.hpp".contents = ""
+
+        [packages]
+        zlib = { git = { url = "https://example.com/zlib.git" } }
+        abseil = { git = { url = "https://example.com/abseil.git" } }
+        fmt = { git = { url = "https://example.com/fmt.git" } }
+    "#};
+
+    fn generate() -> SourceTree {
+        let project: Project = Format::Toml.parse(DESCRIPTION).unwrap();
+        CmakeProject::try_from(project).unwrap().into_tree().unwrap()
+    }
+
+    fn file<'a>(tree: &'a SourceTree, path: &[&str]) -> &'a str {
+        let mut nodes = &tree.children;
+        for (i, part) in path.iter().enumerate() {
+            let node = nodes.iter().find(|n| n.name() == *part).unwrap();
+            match node {
+                SourceNode::Directory { children, .. } => nodes = children,
+                SourceNode::File { contents, .. } => {
+                    assert_eq!(i, path.len() - 1);
+                    return std::str::from_utf8(contents).unwrap();
+                }
+                SourceNode::Symlink { .. } => break,
+            }
+        }
+        panic!("{path:?} is not a file");
+    }
+
+    #[test]
+    fn interface_libraries_reject_compiled_sources() {
+        let description = indoc! {r#"
+            [libraries.math]
+            kind = "interface"
+            sources."math.cpp".contents = ""
+        "#};
+        let project: Project = Format::Toml.parse(description).unwrap();
+
+        match CmakeProject::try_from(project) {
+            Err(Error::InterfaceSource { library, file }) => {
+                assert_eq!((library.as_str(), file.as_str()), ("math", "math.cpp"));
+            }
+            other => panic!("expected an interface source error, got {:?}", other.err()),
         }
+    }
+
+    #[test]
+    fn directories_cannot_be_the_project_root() {
+        let descriptions = [
+            ("src.dir = \".\"", "."),
+            ("testing.dir = \"./\"", "./"),
+            ("libraries.core.dir = \"\"", ""),
+            ("files.\".\".contents = \"\"", "."),
+        ];
+        for (description, name) in descriptions {
+            let project: Project = Format::Toml.parse(description).unwrap();
+
+            match CmakeProject::try_from(project) {
+                Err(Error::UnsafePath(PathError::Empty(entry))) => assert_eq!(entry, name),
+                other => panic!("{description} was not rejected: {:?}", other.err()),
+            }
+        }
+    }
+
+    #[test]
+    fn installed_libraries_cannot_be_named_lib() {
+        let description = indoc! {r#"
+            install = {}
+
+            [libraries.lib]
+            sources."lib.cpp".contents = ""
+        "#};
+        let project: Project = Format::Toml.parse(description).unwrap();
+
+        assert!(matches!(CmakeProject::try_from(project), Err(Error::ReservedLibraryName(name)) if name == "lib"));
+    }
+
+    #[test]
+    fn versions_are_checked_before_generation() {
+        let project: Project = Format::Toml.parse(r#"version = "1.0 LANGUAGES C""#).unwrap();
+
+        assert!(matches!(CmakeProject::try_from(project), Err(Error::InvalidVersion(v)) if v == "1.0 LANGUAGES C"));
+    }
+
+    #[test]
+    fn license_file_must_be_generated() {
+        let description = indoc! {r#"
+            [packaging]
+            license_file = "LICENSE"
+        "#};
+        let project: Project = Format::Toml.parse(description).unwrap();
+
+        let result = CmakeProject::try_from(project).and_then(|generator| generator.into_tree());
+        assert!(matches!(result, Err(Error::MissingLicenseFile(file)) if file == "LICENSE"));
+    }
+
+    #[test]
+    fn config_header_options_must_be_macro_names() {
+        let description = indoc! {r#"
+            [config_header.options]
+            "1bad" = {}
+        "#};
+        let project: Project = Format::Toml.parse(description).unwrap();
+
+        assert!(matches!(CmakeProject::try_from(project), Err(Error::InvalidOptionName(name)) if name == "1bad"));
+    }
+
+    #[test]
+    fn generation_is_deterministic() {
+        assert_eq!(generate(), generate());
+    }
+
+    #[test]
+    fn output_keeps_description_order() {
+        let tree = generate();
+
+        let src = file(&tree, &["src", "CMakeLists.txt"]);
+        assert!(src.contains("    z.cpp\n    a.cpp\n    m.cpp\n    b.cpp\n    y.cpp\n"));
+
+        let tests = file(&tree, &["tests", "CMakeLists.txt"]);
+        assert!(tests.contains("    test_z.cpp\n    test_a.cpp\n    test_m.cpp\n"));
 
-        Ok(SourceTree { children })
+        let libs = file(&tree, &["lib", "CMakeLists.txt"]);
+        assert!(libs.contains("FetchContent_MakeAvailable(zlib abseil fmt)"));
     }
 }
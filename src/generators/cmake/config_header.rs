@@ -0,0 +1,132 @@
+use std::fmt::{self, Write as _};
+
+use indoc::writedoc;
+
+use crate::{
+    Error, Result,
+    project::Project,
+    strings::{cmake_quote, is_identifier, trim_newline},
+    tree::{SourceNode, normalize_name},
+};
+
+/// Template of the version header, relative to the project root.
+const TEMPLATE: &str = "cmake/config.hpp.in";
+
+/// Directory the version header is configured into. Targets add it to their include
+/// directories, so that the header is included as `<name/version.hpp>`.
+pub(super) const GENERATED_INCLUDE_DIR: &str = "${PROJECT_BINARY_DIR}/include";
+
+/// Version header configured into the build directory.
+pub struct CmakeConfigHeader {
+    /// Directory of the header, relative to the generated include directory.
+    pub dir: String,
+    /// Prefix of the version macros. The macros are prefixed rather than named after the
+    /// `PROJECT_VERSION_*` variables so that the headers of several projects can be used
+    /// together.
+    pub prefix: String,
+    /// Option name, help text and default value.
+    pub options: Vec<(String, String, bool)>,
+    /// Whether the header is installed along with the project headers.
+    pub install: bool,
+}
+
+impl CmakeConfigHeader {
+    pub fn from_project(project: &Project) -> Result<Option<Self>> {
+        let Some(config_header) = &project.config_header else {
+            return Ok(None);
+        };
+
+        if let Some(name) = config_header.options.keys().find(|name| !is_identifier(name)) {
+            return Err(Error::InvalidOptionName(name.clone()));
+        }
+
+        let prefix = project
+            .name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+            .collect();
+
+        Ok(Some(Self {
+            dir: normalize_name(&project.name)?,
+            prefix,
+            options: config_header
+                .options
+                .iter()
+                .map(|(name, option)| (name.clone(), option.description.clone(), option.default))
+                .collect(),
+            install: project.install.is_some(),
+        }))
+    }
+
+    /// Writes the options and the rules configuring the header, before the subdirectories of
+    /// the top-level `CMakeLists.txt` so that the options apply to every target.
+    pub fn write(&self, sb: &mut String) -> fmt::Result {
+        if !self.options.is_empty() {
+            for (name, description, default) in &self.options {
+                let default = if *default { "ON" } else { "OFF" };
+                writeln!(sb, "option({name} {} {default})", cmake_quote(description))?;
+            }
+            writeln!(sb)?;
+        }
+
+        let header = format!("{GENERATED_INCLUDE_DIR}/{}/version.hpp", self.dir);
+        writedoc!(
+            sb,
+            r"
+            find_package(Git QUIET)
+            if(GIT_FOUND)
+                execute_process(
+                    COMMAND ${{GIT_EXECUTABLE}} rev-parse --short HEAD
+                    WORKING_DIRECTORY ${{PROJECT_SOURCE_DIR}}
+                    OUTPUT_VARIABLE GIT_COMMIT_HASH
+                    OUTPUT_STRIP_TRAILING_WHITESPACE
+                    ERROR_QUIET
+                )
+            endif()
+            if(NOT GIT_COMMIT_HASH)
+                set(GIT_COMMIT_HASH unknown)
+            endif()
+
+            configure_file({TEMPLATE} {header})
+            "
+        )?;
+
+        if self.install {
+            writeln!(sb, "install(FILES {header} DESTINATION ${{CMAKE_INSTALL_INCLUDEDIR}}/{})", self.dir)?;
+        }
+        writeln!(sb)
+    }
+
+    /// Returns the header template read by `configure_file`.
+    pub fn node(&self) -> Result<SourceNode, fmt::Error> {
+        let prefix = &self.prefix;
+        let mut sb = String::new();
+        writedoc!(
+            sb,
+            r#"
+            #pragma once
+
+            #define {prefix}_VERSION "@PROJECT_VERSION@"
+            #define {prefix}_VERSION_MAJOR @PROJECT_VERSION_MAJOR@
+            #define {prefix}_VERSION_MINOR @PROJECT_VERSION_MINOR@
+            #define {prefix}_VERSION_PATCH @PROJECT_VERSION_PATCH@
+
+            #define {prefix}_GIT_COMMIT "@GIT_COMMIT_HASH@"
+            "#
+        )?;
+
+        if !self.options.is_empty() {
+            writeln!(sb)?;
+            for (name, _, _) in &self.options {
+                writeln!(sb, "#cmakedefine01 {name}")?;
+            }
+        }
+        trim_newline(&mut sb);
+
+        Ok(SourceNode::File {
+            name: TEMPLATE.into(),
+            contents: sb.into_bytes(),
+            mode: None,
+        })
+    }
+}
@@ -1,29 +1,202 @@
-use std::fs;
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
 
-use crate::tree::SourceNode;
+use crate::{
+    Error, Result,
+    project::{Project, decode_base64, is_compiled},
+    tree::{SourceNode, normalize_name},
+};
 
 #[derive(Clone)]
 pub struct SourceFile {
     pub name: String,
-    pub contents: String,
+    pub contents: Contents,
+    pub mode: Option<u32>,
+}
+
+/// Contents of a generated file. Text and binary contents are written the same way for now:
+/// the split marks which files template variable substitution may rewrite once it exists, so
+/// that images and other binary files are always copied byte for byte.
+#[derive(Clone)]
+pub enum Contents {
+    Text(String),
+    /// Contents that are not valid UTF-8 or were given as base64.
+    Binary(Vec<u8>),
+    Symlink(String),
+}
+
+impl Contents {
+    fn from_bytes(bytes: Vec<u8>) -> Self {
+        String::from_utf8(bytes).map_or_else(|e| Contents::Binary(e.into_bytes()), Contents::Text)
+    }
 }
 
 impl SourceFile {
-    pub fn load(name: String, source: &crate::project::SourceFile) -> std::io::Result<Self> {
-        let contents = match source {
-            crate::project::SourceFile::Path(path) => fs::read_to_string(path)?,
-            crate::project::SourceFile::Contents(contents) => contents.clone(),
+    fn new(name: String, contents: Contents) -> Self {
+        Self { name, contents, mode: None }
+    }
+
+    /// Loads a single file, failing for sources that expand to several files.
+    pub fn load_one(
+        name: String,
+        source: &crate::project::SourceFile,
+        project: &Project,
+    ) -> Result<Self> {
+        let mut files = Self::load(name.clone(), source, project)?;
+        match (files.pop(), files.is_empty()) {
+            (Some(file), true) => Ok(file),
+            _ => Err(Error::NotASingleFile { entry: name }),
+        }
+    }
+
+    /// Loads all files a source expands to, named relative to the directory containing `name`.
+    pub fn load(
+        name: String,
+        source: &crate::project::SourceFile,
+        project: &Project,
+    ) -> Result<Vec<Self>> {
+        let entry = name;
+        let name = normalize_name(&entry)?;
+        let read_error = |path: &Path| {
+            let entry = entry.clone();
+            let path = path.to_path_buf();
+            move |source| Error::SourceFile { entry, path, source }
+        };
+
+        let files = match source {
+            crate::project::SourceFile::Path(path) => {
+                let path = project.resolve_path(path);
+                let contents = fs::read(&path).map_err(read_error(&path))?;
+                vec![SourceFile::new(name, Contents::from_bytes(contents))]
+            }
+
+            crate::project::SourceFile::Contents(contents) => {
+                vec![SourceFile::new(name, Contents::Text(contents.clone()))]
+            }
+
+            crate::project::SourceFile::Base64(text) => {
+                let contents = decode_base64(text).map_err(|source| Error::InvalidBase64 {
+                    entry: entry.clone(),
+                    source,
+                })?;
+                vec![SourceFile::new(name, Contents::Binary(contents))]
+            }
+
+            crate::project::SourceFile::Symlink(target) => {
+                vec![SourceFile::new(name, Contents::Symlink(target.clone()))]
+            }
+
+            crate::project::SourceFile::Dir { path } => {
+                let root = project.resolve_path(path);
+                let mut paths = Vec::new();
+                walk_dir(&root, &mut paths).map_err(read_error(&root))?;
+                Self::load_relative(&name, &root, paths, read_error)?
+            }
+
+            crate::project::SourceFile::Glob { pattern } => {
+                let pattern = project.resolve_path(pattern);
+                let pattern = pattern.to_string_lossy();
+                let paths = glob::glob(&pattern)
+                    .map_err(|source| Error::InvalidPattern {
+                        entry: entry.clone(),
+                        pattern: pattern.clone().into_owned(),
+                        source,
+                    })?
+                    .filter_map(|entry| match entry {
+                        Ok(path) if path.is_file() => Some(Ok(path)),
+                        Ok(_) => None,
+                        Err(e) => {
+                            let path = e.path().to_path_buf();
+                            Some(Err(read_error(&path)(e.into())))
+                        }
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Self::load_relative(&name, &glob_base(&pattern), paths, read_error)?
+            }
         };
 
-        Ok(SourceFile { name, contents })
+        Ok(files)
+    }
+
+    fn load_relative<F>(
+        name: &str,
+        root: &Path,
+        paths: Vec<PathBuf>,
+        read_error: impl Fn(&Path) -> F,
+    ) -> Result<Vec<Self>>
+    where
+        F: FnOnce(io::Error) -> Error,
+    {
+        paths
+            .into_iter()
+            .map(|path| {
+                let relative = match path.strip_prefix(root) {
+                    Ok(relative) if !relative.as_os_str().is_empty() => relative,
+                    _ => Path::new(path.file_name().unwrap_or_default()),
+                };
+                let name = std::iter::once(name.to_owned())
+                    .chain(relative.iter().map(|c| c.to_string_lossy().into_owned()))
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let contents = fs::read(&path).map_err(read_error(&path))?;
+
+                Ok(SourceFile::new(name, Contents::from_bytes(contents)))
+            })
+            .collect()
+    }
+
+    /// Whether the file has to be listed as a source of a CMake target.
+    pub fn is_compiled(&self) -> bool {
+        is_compiled(&self.name)
+    }
+}
+
+fn walk_dir(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|e| Ok(e?.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            walk_dir(&path, paths)?;
+        } else {
+            paths.push(path);
+        }
     }
+
+    Ok(())
+}
+
+/// Returns the leading part of a glob pattern that contains no wildcards.
+fn glob_base(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|c| match c {
+            Component::Normal(s) => !s.to_string_lossy().contains(['*', '?', '[']),
+            _ => true,
+        })
+        .collect()
 }
 
-impl From<SourceFile> for SourceNode {
-    fn from(value: SourceFile) -> Self {
-        Self::File {
-            name: value.name.clone().into(),
-            contents: value.contents.clone().into_bytes(),
+impl SourceFile {
+    pub fn insert_into(self, nodes: &mut Vec<SourceNode>) {
+        match self.contents {
+            Contents::Text(text) => {
+                SourceNode::insert_file(nodes, Path::new(&self.name), text.into_bytes(), self.mode);
+            }
+            Contents::Binary(bytes) => {
+                SourceNode::insert_file(nodes, Path::new(&self.name), bytes, self.mode);
+            }
+            Contents::Symlink(target) => SourceNode::insert(
+                nodes,
+                SourceNode::Symlink {
+                    name: self.name.into(),
+                    target: target.into(),
+                },
+            ),
         }
     }
 }
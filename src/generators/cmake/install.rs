@@ -0,0 +1,116 @@
+use std::fmt::{self, Write as _};
+
+use indoc::writedoc;
+
+use crate::{
+    Error, Result,
+    project::{Compatibility, Project},
+    strings::trim_newline,
+    tree::SourceNode,
+};
+
+/// Version used when an installed, packaged or versioned project does not declare one.
+pub const DEFAULT_VERSION: &str = "0.1.0";
+
+/// Name of the project library in the namespace of the export set, reserved for it.
+pub const LIBRARY_EXPORT_NAME: &str = "lib";
+
+/// Export set the installed libraries are added to.
+#[derive(Clone)]
+pub struct ExportSet {
+    pub name: String,
+    pub namespace: String,
+}
+
+/// Package config export of an installed project.
+pub struct CmakeInstall {
+    pub name: String,
+    pub namespace: String,
+    pub compatibility: Compatibility,
+}
+
+impl CmakeInstall {
+    pub fn from_project(project: &Project) -> Result<Option<Self>> {
+        let Some(install) = &project.install else {
+            return Ok(None);
+        };
+
+        if let Some((library, package)) = project.exported_packages().into_iter().next() {
+            return Err(Error::ExportedPackage { library, package });
+        }
+
+        Ok(Some(Self {
+            name: project.name.clone(),
+            namespace: install.namespace.clone().unwrap_or_else(|| project.name.clone()),
+            compatibility: install.compatibility,
+        }))
+    }
+
+    /// Name of the export set holding every installed library.
+    pub fn export_set(&self) -> String {
+        format!("{}Targets", self.name)
+    }
+
+    pub fn export(&self) -> ExportSet {
+        ExportSet {
+            name: self.export_set(),
+            namespace: self.namespace.clone(),
+        }
+    }
+
+    /// Writes the package config export rules of the top-level `CMakeLists.txt`.
+    pub fn write(&self, sb: &mut String) -> fmt::Result {
+        let name = &self.name;
+        let destination = format!("${{CMAKE_INSTALL_LIBDIR}}/cmake/{name}");
+
+        writedoc!(
+            sb,
+            r"
+            include(CMakePackageConfigHelpers)
+
+            install(EXPORT {export_set}
+                NAMESPACE {namespace}::
+                DESTINATION {destination}
+            )
+
+            configure_package_config_file(
+                cmake/{name}Config.cmake.in
+                ${{CMAKE_CURRENT_BINARY_DIR}}/{name}Config.cmake
+                INSTALL_DESTINATION {destination}
+            )
+
+            write_basic_package_version_file(
+                ${{CMAKE_CURRENT_BINARY_DIR}}/{name}ConfigVersion.cmake
+                COMPATIBILITY {compatibility}
+            )
+
+            install(FILES
+                ${{CMAKE_CURRENT_BINARY_DIR}}/{name}Config.cmake
+                ${{CMAKE_CURRENT_BINARY_DIR}}/{name}ConfigVersion.cmake
+                DESTINATION {destination}
+            )
+            ",
+            export_set = self.export_set(),
+            namespace = self.namespace,
+            compatibility = self.compatibility.as_cmake(),
+        )
+    }
+
+    /// Returns the `cmake/<name>Config.cmake.in` template read by `configure_package_config_file`.
+    pub fn config_node(&self) -> Result<SourceNode, fmt::Error> {
+        let mut sb = String::new();
+        writeln!(sb, "@PACKAGE_INIT@")?;
+        writeln!(sb)?;
+
+        writeln!(sb, "include(\"${{CMAKE_CURRENT_LIST_DIR}}/{}.cmake\")", self.export_set())?;
+        writeln!(sb)?;
+        writeln!(sb, "check_required_components({})", self.name)?;
+        trim_newline(&mut sb);
+
+        Ok(SourceNode::File {
+            name: format!("cmake/{}Config.cmake.in", self.name).into(),
+            contents: sb.into_bytes(),
+            mode: None,
+        })
+    }
+}
@@ -0,0 +1,340 @@
+use std::fmt::{self, Write as _};
+
+use indexmap::IndexMap;
+use indoc::writedoc;
+
+use crate::{
+    Error, Result,
+    project::{LibraryKind, Project, is_compiled},
+    strings::trim_newline,
+    tree::{SourceNode, normalize_name},
+};
+
+use super::{
+    config_header::GENERATED_INCLUDE_DIR,
+    file::SourceFile,
+    install::{CmakeInstall, ExportSet, LIBRARY_EXPORT_NAME},
+};
+
+/// Internal library generated into its own subdirectory of the sources directory.
+pub struct CmakeLibrary {
+    pub name: String,
+    pub dir: String,
+    pub kind: Option<LibraryKind>,
+    pub files: Vec<SourceFile>,
+    pub public_dependencies: Vec<String>,
+    pub private_dependencies: Vec<String>,
+    pub export: Option<ExportSet>,
+    /// Whether the library is installed into the binary packages without being exported.
+    pub package: bool,
+    pub config_header: bool,
+}
+
+impl CmakeLibrary {
+    /// Loads the internal libraries, ordered so that dependencies come first.
+    pub fn from_project(project: &Project) -> Result<Vec<Self>> {
+        let export = CmakeInstall::from_project(project)?.map(|i| i.export());
+        if export.is_some() && project.libraries.contains_key(LIBRARY_EXPORT_NAME) {
+            return Err(Error::ReservedLibraryName(LIBRARY_EXPORT_NAME.to_owned()));
+        }
+
+        project
+            .library_order()?
+            .into_iter()
+            .map(|name| {
+                let library = &project.libraries[name];
+                let dir = normalize_name(library.dir.as_deref().unwrap_or(name))?;
+
+                let mut files = Vec::new();
+                for (file, source) in library.sources.iter().chain(&library.headers) {
+                    files.extend(SourceFile::load(file.clone(), source, project)?);
+                }
+                if library.kind == Some(LibraryKind::Interface)
+                    && let Some(source) = files.iter().find(|f| f.is_compiled())
+                {
+                    return Err(Error::InterfaceSource {
+                        library: name.to_owned(),
+                        file: source.name.clone(),
+                    });
+                }
+
+                Ok(CmakeLibrary {
+                    name: name.to_owned(),
+                    dir,
+                    kind: library.kind,
+                    files,
+                    public_dependencies: library.public_dependencies.clone(),
+                    private_dependencies: library.private_dependencies.clone(),
+                    export: export.clone(),
+                    package: project.packaging.is_some(),
+                    config_header: project.config_header.is_some(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn into_node(self) -> Result<SourceNode, fmt::Error> {
+        let mut cmakelists = String::new();
+        write_library(&mut cmakelists, &self)?;
+        trim_newline(&mut cmakelists);
+
+        let mut children = vec![SourceNode::File {
+            name: "CMakeLists.txt".into(),
+            contents: cmakelists.into_bytes(),
+            mode: None,
+        }];
+
+        for file in self.files {
+            file.insert_into(&mut children);
+        }
+
+        Ok(SourceNode::Directory { name: self.dir.into(), children })
+    }
+}
+
+fn write_library(sb: &mut String, library: &CmakeLibrary) -> fmt::Result {
+    let (sources, headers): (Vec<&str>, Vec<&str>) = library
+        .files
+        .iter()
+        .map(|f| f.name.as_str())
+        .partition(|name| is_compiled(name));
+
+    LibraryTarget {
+        name: &library.name,
+        kind: library.kind,
+        sources: &sources,
+        public_headers: None,
+        public_dependencies: &library.public_dependencies,
+        private_dependencies: &library.private_dependencies,
+        export: library.export.as_ref().map(|set| Export {
+            set,
+            name: &library.name,
+            headers,
+            include_dir: &library.name,
+        }),
+        package: library.package,
+        config_header: library.config_header,
+    }
+    .write(sb)
+}
+
+/// Library target with its include directories and dependencies.
+pub(super) struct LibraryTarget<'a> {
+    pub name: &'a str,
+    pub kind: Option<LibraryKind>,
+    /// Compiled sources. Ignored for header-only libraries.
+    pub sources: &'a [&'a str],
+    /// Directory holding the public headers, relative to the project root. Headers are next
+    /// to the sources when unset.
+    pub public_headers: Option<&'a str>,
+    pub public_dependencies: &'a [String],
+    pub private_dependencies: &'a [String],
+    /// Install rules, when the library is installed.
+    pub export: Option<Export<'a>>,
+    /// Whether the library is installed into the binary packages when it is not exported.
+    /// Only libraries that may be loaded at runtime are installed then.
+    pub package: bool,
+    /// Whether consumers include the generated version header.
+    pub config_header: bool,
+}
+
+/// Installation of a library into an export set.
+pub(super) struct Export<'a> {
+    pub set: &'a ExportSet,
+    /// Name of the target in the namespace of the export set.
+    pub name: &'a str,
+    /// Headers installed for libraries without a public headers directory, relative to the
+    /// library directory.
+    pub headers: Vec<&'a str>,
+    /// Subdirectory of the installed include directory holding the headers.
+    pub include_dir: &'a str,
+}
+
+impl Export<'_> {
+    fn include_destination(&self) -> String {
+        match self.include_dir {
+            "" => "${CMAKE_INSTALL_INCLUDEDIR}".to_owned(),
+            dir => format!("${{CMAKE_INSTALL_INCLUDEDIR}}/{dir}"),
+        }
+    }
+}
+
+impl LibraryTarget<'_> {
+    pub fn write(&self, sb: &mut String) -> fmt::Result {
+        let name = self.name;
+        let keyword = match self.kind {
+            None => "",
+            Some(LibraryKind::Static) => " STATIC",
+            Some(LibraryKind::Shared) => " SHARED",
+            Some(LibraryKind::Object) => " OBJECT",
+            Some(LibraryKind::Interface) => " INTERFACE",
+        };
+
+        if self.kind == Some(LibraryKind::Interface) {
+            writeln!(sb, "add_library({name} INTERFACE)")?;
+        } else {
+            writeln!(sb, "add_library({name}{keyword}")?;
+            for source in self.sources {
+                writeln!(sb, "    {source}")?;
+            }
+            writeln!(sb, ")")?;
+        }
+        writeln!(sb)?;
+
+        if self.kind == Some(LibraryKind::Shared) {
+            writedoc!(
+                sb,
+                r"
+                include(GenerateExportHeader)
+                generate_export_header({name})
+
+                set_target_properties({name} PROPERTIES
+                    CXX_VISIBILITY_PRESET hidden
+                    VISIBILITY_INLINES_HIDDEN ON
+                )
+
+                "
+            )?;
+        }
+
+        self.write_include_directories(sb)?;
+        self.write_link_libraries(sb)?;
+
+        if let Some(export) = &self.export {
+            self.write_install(sb, export)?;
+        } else if self.package && matches!(self.kind, None | Some(LibraryKind::Shared)) {
+            writeln!(sb, "install(TARGETS {name})")?;
+            writeln!(sb)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_include_directories(&self, sb: &mut String) -> fmt::Result {
+        let mut public = Vec::new();
+        let mut private = Vec::new();
+        match (self.public_headers, &self.export) {
+            (None, None) => public.push(".".to_owned()),
+            // Paths inside the source tree cannot be exported.
+            (None, Some(export)) => {
+                public.push("$<BUILD_INTERFACE:${CMAKE_CURRENT_SOURCE_DIR}>".to_owned());
+                public.push(format!("$<INSTALL_INTERFACE:{}>", export.include_destination()));
+            }
+            (Some(dir), export) => {
+                public.push(format!("$<BUILD_INTERFACE:${{PROJECT_SOURCE_DIR}}/{dir}>"));
+                if let Some(export) = export {
+                    public.push(format!("$<INSTALL_INTERFACE:{}>", export.include_destination()));
+                }
+                private.push(".".to_owned());
+            }
+        }
+        if self.config_header {
+            match (self.public_headers, &self.export) {
+                (_, None) => public.push(GENERATED_INCLUDE_DIR.to_owned()),
+                // The public headers directory is already installed at the include root.
+                (Some(_), Some(_)) => public.push(format!("$<BUILD_INTERFACE:{GENERATED_INCLUDE_DIR}>")),
+                (None, Some(_)) => {
+                    public.push(format!("$<BUILD_INTERFACE:{GENERATED_INCLUDE_DIR}>"));
+                    public.push("$<INSTALL_INTERFACE:${CMAKE_INSTALL_INCLUDEDIR}>".to_owned());
+                }
+            }
+        }
+        if self.kind == Some(LibraryKind::Shared) {
+            // The generated export header.
+            public.push("$<BUILD_INTERFACE:${CMAKE_CURRENT_BINARY_DIR}>".to_owned());
+        }
+
+        let name = self.name;
+        let visibility = self.visibility();
+        if let ([dir], []) = (public.as_slice(), private.as_slice()) {
+            writeln!(sb, "target_include_directories({name} {} {dir})", visibility.0)?;
+            writeln!(sb)?;
+            return Ok(());
+        }
+
+        writeln!(sb, "target_include_directories({name}")?;
+        write_visibility_groups(sb, [(visibility.0, public.as_slice()), (visibility.1, private.as_slice())])?;
+        writeln!(sb, ")")?;
+        writeln!(sb)
+    }
+
+    fn write_link_libraries(&self, sb: &mut String) -> fmt::Result {
+        if self.public_dependencies.is_empty() && self.private_dependencies.is_empty() {
+            return Ok(());
+        }
+
+        let (public, private) = self.visibility();
+        writeln!(sb, "target_link_libraries({}", self.name)?;
+        write_visibility_groups(sb, [(public, self.public_dependencies), (private, self.private_dependencies)])?;
+        writeln!(sb, ")")?;
+        writeln!(sb)
+    }
+
+    fn write_install(&self, sb: &mut String, export: &Export) -> fmt::Result {
+        let name = self.name;
+        writeln!(sb, "add_library({}::{} ALIAS {name})", export.set.namespace, export.name)?;
+        if export.name != name {
+            writeln!(sb, "set_target_properties({name} PROPERTIES EXPORT_NAME {})", export.name)?;
+        }
+        writeln!(sb)?;
+
+        writeln!(sb, "install(TARGETS {name} EXPORT {})", export.set.name)?;
+
+        let destination = export.include_destination();
+        let mut header_dirs: IndexMap<&str, Vec<&str>> = IndexMap::new();
+        for header in &export.headers {
+            let dir = header.rsplit_once('/').map_or("", |(dir, _)| dir);
+            header_dirs.entry(dir).or_default().push(header);
+        }
+        for (dir, headers) in header_dirs {
+            writeln!(sb, "install(FILES")?;
+            for header in headers {
+                writeln!(sb, "    {header}")?;
+            }
+            match dir {
+                "" => writeln!(sb, "    DESTINATION {destination}")?,
+                dir => writeln!(sb, "    DESTINATION {destination}/{dir}")?,
+            }
+            writeln!(sb, ")")?;
+        }
+
+        if let Some(dir) = self.public_headers {
+            writeln!(sb, "install(DIRECTORY ${{PROJECT_SOURCE_DIR}}/{dir}/ DESTINATION {destination})")?;
+        }
+
+        if self.kind == Some(LibraryKind::Shared) {
+            writeln!(
+                sb,
+                "install(FILES ${{CMAKE_CURRENT_BINARY_DIR}}/{name}_export.h DESTINATION {destination})",
+            )?;
+        }
+        writeln!(sb)
+    }
+
+    /// Keywords for public and private usage requirements. Everything a header-only library
+    /// uses is needed by its consumers.
+    fn visibility(&self) -> (&'static str, &'static str) {
+        match self.kind {
+            Some(LibraryKind::Interface) => ("INTERFACE", "INTERFACE"),
+            _ => ("PUBLIC", "PRIVATE"),
+        }
+    }
+}
+
+fn write_visibility_groups<'a>(
+    sb: &mut String,
+    groups: impl IntoIterator<Item = (&'a str, &'a [String])>,
+) -> fmt::Result {
+    for (visibility, items) in groups {
+        if items.is_empty() {
+            continue;
+        }
+
+        writeln!(sb, "    {visibility}")?;
+        for item in items {
+            writeln!(sb, "        {item}")?;
+        }
+    }
+
+    Ok(())
+}
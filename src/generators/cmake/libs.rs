@@ -1,6 +1,6 @@
-use std::{fmt::Write as _, io};
+use std::fmt::{self, Write as _};
 
-use crate::{project::Project, tree::SourceNode};
+use crate::{Result, project::Project, tree::SourceNode};
 
 #[derive(Default)]
 pub struct FetchPackage {
@@ -16,7 +16,7 @@ pub struct CmakeLibs {
 }
 
 impl CmakeLibs {
-    pub fn from_project(project: &Project) -> Result<Option<Self>, io::Error> {
+    pub fn from_project(project: &Project) -> Result<Option<Self>> {
         if project.packages.is_empty() {
             return Ok(None);
         }
@@ -48,7 +48,7 @@ impl CmakeLibs {
         Ok(Some(Self { fetch_packages }))
     }
 
-    pub fn into_node(self) -> Result<SourceNode, anyhow::Error> {
+    pub fn into_node(self) -> Result<SourceNode, fmt::Error> {
         let mut sb = String::new();
         write_libs(&mut sb, &self.fetch_packages)?;
 
@@ -57,12 +57,13 @@ impl CmakeLibs {
             children: vec![SourceNode::File {
                 name: "CMakeLists.txt".into(),
                 contents: sb.into_bytes(),
+                mode: None,
             }],
         })
     }
 }
 
-fn write_libs(sb: &mut String, fetch_packages: &Vec<FetchPackage>) -> Result<(), anyhow::Error> {
+fn write_libs(sb: &mut String, fetch_packages: &Vec<FetchPackage>) -> fmt::Result {
     writeln!(sb, "include(FetchContent)")?;
     writeln!(sb)?;
     for package in fetch_packages {
@@ -82,7 +83,7 @@ fn write_libs(sb: &mut String, fetch_packages: &Vec<FetchPackage>) -> Result<(),
     Ok(())
 }
 
-fn write_package(sb: &mut String, package: &FetchPackage) -> Result<(), anyhow::Error> {
+fn write_package(sb: &mut String, package: &FetchPackage) -> fmt::Result {
     writeln!(sb, "FetchContent_Declare(")?;
     writeln!(sb, "    {}", package.name)?;
     if let Some(url) = &package.url {
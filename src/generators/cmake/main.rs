@@ -2,32 +2,70 @@ use std::fmt::Write as _;
 
 use indoc::writedoc;
 
-use crate::{project::Project, tree::SourceNode};
+use crate::{
+    Error, Result,
+    project::Project,
+    strings::{cmake_quote, is_version},
+    tree::{SourceNode, normalize_name},
+};
+
+use super::{
+    config_header::CmakeConfigHeader,
+    install::{CmakeInstall, DEFAULT_VERSION},
+    packaging::CmakePackaging,
+};
 
 pub struct CmakeMain {
     pub name: String,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub homepage: Option<String>,
+    /// Languages enabled by `project()`.
+    pub languages: Vec<&'static str>,
     pub subdirectories: Vec<String>,
     pub enable_testing: bool,
+    pub config_header: Option<CmakeConfigHeader>,
+    pub install: Option<CmakeInstall>,
+    pub packaging: Option<CmakePackaging>,
 }
 
 impl TryFrom<&Project> for CmakeMain {
-    type Error = anyhow::Error;
+    type Error = Error;
 
-    fn try_from(value: &Project) -> Result<Self, Self::Error> {
+    fn try_from(value: &Project) -> Result<Self> {
         let mut subdirectories = Vec::new();
-        subdirectories.push(value.src.dir.clone());
+        subdirectories.push(normalize_name(&value.src.dir)?);
         if !value.packages.is_empty() {
             subdirectories.push("lib".into());
         }
 
         if let Some(ref testing) = value.testing {
-            subdirectories.push(testing.dir.clone());
+            subdirectories.push(normalize_name(&testing.dir)?);
         }
 
+        let install = CmakeInstall::from_project(value)?;
+        let packaging = CmakePackaging::from_project(value);
+        let config_header = CmakeConfigHeader::from_project(value)?;
+        let version = match &value.version {
+            Some(version) if !is_version(version) => return Err(Error::InvalidVersion(version.clone())),
+            Some(version) => Some(version.clone()),
+            None if install.is_some() || packaging.is_some() || config_header.is_some() => {
+                Some(DEFAULT_VERSION.to_owned())
+            }
+            None => None,
+        };
+
         Ok(Self {
             name: value.name.clone(),
+            version,
+            description: value.description.clone(),
+            homepage: value.homepage.clone(),
+            languages: vec!["CXX"],
             subdirectories,
             enable_testing: value.testing.is_some(),
+            config_header,
+            install,
+            packaging,
         })
     }
 }
@@ -37,35 +75,81 @@ impl TryFrom<CmakeMain> for SourceNode {
 
     fn try_from(value: CmakeMain) -> Result<Self, Self::Error> {
         let mut sb = String::new();
-        write_main(&mut sb, &value.name, &value.subdirectories, value.enable_testing)?;
+        write_main(&mut sb, &value)?;
         Ok(Self::File {
             name: "CMakeLists.txt".into(),
             contents: sb.into_bytes(),
+            mode: None,
         })
     }
 }
 
-fn write_main(sb: &mut String, name: &str, subdirectories: &Vec<String>, enable_testing: bool) -> std::fmt::Result {
+fn write_main(sb: &mut String, main: &CmakeMain) -> std::fmt::Result {
     writedoc!(
         sb,
         r"
-        cmake_minimum_required(VERSION 3.10)
+        cmake_minimum_required(VERSION 3.14)
 
         set(CMAKE_EXPORT_COMPILE_COMMANDS ON)
 
-        project({name})
         "
     )?;
+    write_project(sb, main)?;
     writeln!(sb)?;
 
-    if enable_testing {
+    if main.install.is_some() || main.packaging.is_some() {
+        writeln!(sb, "include(GNUInstallDirs)")?;
+        writeln!(sb)?;
+    }
+
+    if main.enable_testing {
         writeln!(sb, "enable_testing()")?;
         writeln!(sb)?;
     }
 
-    for dir in subdirectories {
+    if let Some(config_header) = &main.config_header {
+        config_header.write(sb)?;
+    }
+
+    for dir in &main.subdirectories {
         writeln!(sb, "add_subdirectory({dir})")?;
     }
 
+    if let Some(install) = &main.install {
+        writeln!(sb)?;
+        install.write(sb)?;
+    }
+
+    if let Some(packaging) = &main.packaging {
+        writeln!(sb)?;
+        packaging.write(sb)?;
+    }
+
     Ok(())
 }
+
+/// Writes the `project()` call, on one line unless it has a description or homepage.
+fn write_project(sb: &mut String, main: &CmakeMain) -> std::fmt::Result {
+    let mut options = Vec::new();
+    if let Some(version) = &main.version {
+        options.push(format!("VERSION {version}"));
+    }
+    if let Some(description) = &main.description {
+        options.push(format!("DESCRIPTION {}", cmake_quote(description)));
+    }
+    if let Some(homepage) = &main.homepage {
+        options.push(format!("HOMEPAGE_URL {}", cmake_quote(homepage)));
+    }
+    options.push(format!("LANGUAGES {}", main.languages.join(" ")));
+
+    let name = &main.name;
+    if main.description.is_none() && main.homepage.is_none() {
+        return writeln!(sb, "project({name} {})", options.join(" "));
+    }
+
+    writeln!(sb, "project({name}")?;
+    for option in options {
+        writeln!(sb, "    {option}")?;
+    }
+    writeln!(sb, ")")
+}
@@ -0,0 +1,68 @@
+use std::fmt::{self, Write as _};
+
+use crate::{
+    project::{PackageGenerator, Project},
+    strings::{cmake_escape, cmake_quote},
+};
+
+/// CPack configuration appended to the top-level `CMakeLists.txt`.
+pub struct CmakePackaging {
+    pub generators: Vec<PackageGenerator>,
+    pub maintainer: Option<String>,
+    pub license_file: Option<String>,
+    pub description: Option<String>,
+    pub license: Option<String>,
+    pub dependencies: Vec<String>,
+}
+
+impl CmakePackaging {
+    pub fn from_project(project: &Project) -> Option<Self> {
+        let packaging = project.packaging.as_ref()?;
+        Some(Self {
+            generators: packaging.generators.clone(),
+            maintainer: packaging.maintainer.clone().or_else(|| project.authors.first().cloned()),
+            license_file: packaging.license_file.clone(),
+            // CPack falls back to the description and homepage passed to `project()`.
+            description: packaging.description.clone(),
+            license: project.license.clone(),
+            dependencies: packaging.dependencies.clone(),
+        })
+    }
+
+    pub fn write(&self, sb: &mut String) -> fmt::Result {
+        let generators: Vec<&str> = self.generators.iter().map(|g| g.as_cmake()).collect();
+        writeln!(sb, "set(CPACK_GENERATOR {})", cmake_quote(&generators.join(";")))?;
+
+        if let Some(maintainer) = &self.maintainer {
+            writeln!(sb, "set(CPACK_PACKAGE_CONTACT {})", cmake_quote(maintainer))?;
+        }
+
+        if let Some(description) = &self.description {
+            writeln!(sb, "set(CPACK_PACKAGE_DESCRIPTION_SUMMARY {})", cmake_quote(description))?;
+        }
+
+        if let Some(license_file) = &self.license_file {
+            let license_file = cmake_escape(license_file);
+            writeln!(sb, "set(CPACK_RESOURCE_FILE_LICENSE \"${{PROJECT_SOURCE_DIR}}/{license_file}\")")?;
+        }
+
+        if let Some(license) = &self.license
+            && self.generators.contains(&PackageGenerator::Rpm)
+        {
+            writeln!(sb, "set(CPACK_RPM_PACKAGE_LICENSE {})", cmake_quote(license))?;
+        }
+
+        if !self.dependencies.is_empty() {
+            let dependencies = cmake_quote(&self.dependencies.join(", "));
+            if self.generators.contains(&PackageGenerator::Deb) {
+                writeln!(sb, "set(CPACK_DEBIAN_PACKAGE_DEPENDS {dependencies})")?;
+            }
+            if self.generators.contains(&PackageGenerator::Rpm) {
+                writeln!(sb, "set(CPACK_RPM_PACKAGE_REQUIRES {dependencies})")?;
+            }
+        }
+
+        writeln!(sb)?;
+        writeln!(sb, "include(CPack)")
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt::{self, Write as _};
+
+use crate::{
+    project::{LibraryKind, Project},
+    strings::trim_newline,
+    tree::SourceNode,
+};
+
+/// pkg-config file of an installed project, configured next to the project library.
+pub struct PkgConfig {
+    pub name: String,
+    pub description: String,
+    pub homepage: Option<String>,
+    /// Linkable libraries, dependents first.
+    pub libs: Vec<String>,
+    /// Subdirectories of the installed include directory holding the headers.
+    pub include_dirs: Vec<String>,
+}
+
+impl PkgConfig {
+    pub fn from_project(project: &Project) -> Option<Self> {
+        if !project.install.as_ref()?.pkg_config {
+            return None;
+        }
+
+        Some(Self {
+            name: project.name.clone(),
+            description: project
+                .description
+                .as_deref()
+                .map_or_else(|| project.name.clone(), |d| d.split_whitespace().collect::<Vec<_>>().join(" ")),
+            homepage: project.homepage.clone(),
+            libs: Vec::new(),
+            include_dirs: Vec::new(),
+        })
+    }
+
+    /// Adds an installed library. Libraries have to be added dependents first so that static
+    /// linking resolves.
+    pub fn add_library(&mut self, name: &str, kind: Option<LibraryKind>, include_dir: &str) {
+        if !matches!(kind, Some(LibraryKind::Object | LibraryKind::Interface)) {
+            self.libs.push(name.to_owned());
+        }
+        self.add_include_dir(include_dir);
+    }
+
+    pub fn add_include_dir(&mut self, include_dir: &str) {
+        if !self.include_dirs.iter().any(|d| d == include_dir) {
+            self.include_dirs.push(include_dir.to_owned());
+        }
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("{}.pc.in", self.name)
+    }
+
+    /// Writes the rules configuring and installing the pkg-config file.
+    pub fn write(&self, sb: &mut String) -> fmt::Result {
+        let name = &self.name;
+        writeln!(
+            sb,
+            "configure_file({} ${{PROJECT_BINARY_DIR}}/{name}.pc @ONLY)",
+            self.file_name(),
+        )?;
+        writeln!(
+            sb,
+            "install(FILES ${{PROJECT_BINARY_DIR}}/{name}.pc DESTINATION ${{CMAKE_INSTALL_LIBDIR}}/pkgconfig)",
+        )?;
+        writeln!(sb)
+    }
+
+    /// Returns the `<name>.pc.in` template read by `configure_file`.
+    pub fn node(&self) -> Result<SourceNode, fmt::Error> {
+        let mut sb = String::new();
+        writeln!(sb, "prefix=@CMAKE_INSTALL_PREFIX@")?;
+        writeln!(sb, "exec_prefix=${{prefix}}")?;
+        writeln!(sb, "libdir=${{prefix}}/@CMAKE_INSTALL_LIBDIR@")?;
+        writeln!(sb, "includedir=${{prefix}}/@CMAKE_INSTALL_INCLUDEDIR@")?;
+        writeln!(sb)?;
+        writeln!(sb, "Name: {}", self.name)?;
+        writeln!(sb, "Description: {}", self.description)?;
+        writeln!(sb, "Version: @PROJECT_VERSION@")?;
+        if let Some(homepage) = &self.homepage {
+            writeln!(sb, "URL: {homepage}")?;
+        }
+
+        if !self.libs.is_empty() {
+            write!(sb, "Libs: -L${{libdir}}")?;
+            for lib in &self.libs {
+                write!(sb, " -l{lib}")?;
+            }
+            writeln!(sb)?;
+        }
+
+        if !self.include_dirs.is_empty() {
+            write!(sb, "Cflags:")?;
+            for dir in &self.include_dirs {
+                match dir.as_str() {
+                    "" => write!(sb, " -I${{includedir}}")?,
+                    dir => write!(sb, " -I${{includedir}}/{dir}")?,
+                }
+            }
+            writeln!(sb)?;
+        }
+        trim_newline(&mut sb);
+
+        Ok(SourceNode::File {
+            name: self.file_name().into(),
+            contents: sb.into_bytes(),
+            mode: None,
+        })
+    }
+}
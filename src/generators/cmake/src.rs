@@ -1,13 +1,53 @@
-use std::fmt::Write as _;
+use std::fmt::{self, Write as _};
 
-use crate::{project::Project, strings::trim_newline, tree::SourceNode};
+use indexmap::IndexMap;
 
-use super::file::SourceFile;
+use crate::{
+    Error, Result,
+    project::{Layout, LibraryKind, Project, ProjectKind},
+    strings::trim_newline,
+    tree::{SourceNode, normalize_name},
+};
+
+use super::{
+    config_header::GENERATED_INCLUDE_DIR,
+    file::SourceFile,
+    install::{CmakeInstall, ExportSet, LIBRARY_EXPORT_NAME},
+    library::{CmakeLibrary, Export, LibraryTarget},
+    pkg_config::PkgConfig,
+};
+
+/// Directory, relative to the project root, that holds the runtime assets.
+pub(super) const ASSETS_DIR: &str = "assets";
+
+/// Directory, relative to the project root, that holds the public headers with the
+/// [`Layout::Include`] layout.
+const INCLUDE_DIR: &str = "include";
 
 pub(super) struct CmakeSrc {
     pub dir: String,
     pub files: Vec<SourceFile>,
+    /// Public headers placed outside of the sources directory, named relative to the project
+    /// root.
+    pub public_headers: Vec<SourceFile>,
+    pub libraries: Vec<CmakeLibrary>,
     pub targets: Vec<Target>,
+    /// Runtime assets, named relative to the project root.
+    pub assets: Vec<SourceFile>,
+    pub copy_assets: Option<CopyAssets>,
+    pub pkg_config: Option<PkgConfig>,
+}
+
+/// Copy of the runtime assets next to the executables, redone whenever an asset changes.
+pub struct CopyAssets {
+    /// Name of the custom target doing the copy.
+    pub target: String,
+    /// Executable whose output directory the assets are copied to. Every executable shares it.
+    pub executable: String,
+    /// Asset files, relative to the project root.
+    pub files: Vec<String>,
+    /// Whether the assets are installed next to the installed executables.
+    pub install: bool,
 }
 
 pub enum Target {
@@ -15,94 +55,269 @@ pub enum Target {
         name: String,
         sources: Vec<String>,
         link_libraries: Vec<String>,
+        /// Target copying the runtime assets, built before the executable.
+        assets_target: Option<String>,
+        install: bool,
+        /// Whether the generated version header has to be added to the include directories,
+        /// for executables not getting it from the project library.
+        config_header: bool,
     },
 
     Lib {
         sources: Vec<String>,
         name: String,
+        kind: Option<LibraryKind>,
+        public_headers: Option<&'static str>,
+        link_libraries: Vec<String>,
+        export: Option<LibExport>,
+        package: bool,
+        config_header: bool,
     },
 }
 
-impl TryFrom<&Project> for CmakeSrc {
-    type Error = anyhow::Error;
+/// Installation of the project library.
+pub struct LibExport {
+    set: ExportSet,
+    /// Headers installed for the flat layout.
+    headers: Vec<String>,
+    /// Subdirectory of the installed include directory holding the headers.
+    include_dir: String,
+}
 
-    fn try_from(value: &Project) -> Result<Self, Self::Error> {
-        let main_file = SourceFile::load(value.src.main_file.name.clone(), &value.src.main_file.source)?;
+impl TryFrom<&Project> for CmakeSrc {
+    type Error = Error;
 
-        let sources: Vec<SourceFile> = value
-            .src
-            .sources
-            .iter()
-            .map(|(n, s)| SourceFile::load(n.clone(), s))
-            .collect::<Result<Vec<_>, _>>()?;
+    fn try_from(value: &Project) -> Result<Self> {
+        let is_library = value.kind == ProjectKind::Library;
+        let main_file = if is_library {
+            None
+        } else {
+            Some(SourceFile::load_one(value.src.main_file.name.clone(), &value.src.main_file.source, value)?)
+        };
+        let sources = load_all(&value.src.sources, value)?;
+        let private_headers = load_all(&value.src.private_headers, value)?;
+        let (headers, public_headers) = match value.src.layout {
+            Layout::Flat => (load_all(&value.src.headers, value)?, Vec::new()),
+            Layout::Include => {
+                let dir = format!("{INCLUDE_DIR}/{}", normalize_name(&value.name)?);
+                let mut headers = Vec::new();
+                for (name, source) in &value.src.headers {
+                    let name = format!("{dir}/{}", normalize_name(name)?);
+                    headers.extend(SourceFile::load(name, source, value)?);
+                }
+                (Vec::new(), headers)
+            }
+        };
 
-        let headers: Vec<SourceFile> = value
-            .src
-            .headers
+        let mut files: Vec<SourceFile> = main_file
             .iter()
-            .map(|(n, s)| SourceFile::load(n.clone(), s))
-            .collect::<Result<Vec<_>, _>>()?;
-
-        let files = std::iter::once(&main_file)
             .chain(sources.iter())
             .chain(headers.iter())
+            .chain(private_headers.iter())
             .cloned()
             .collect();
 
+        let export = CmakeInstall::from_project(value)?.map(|i| i.export());
+        let include_dir = match value.src.layout {
+            Layout::Flat => normalize_name(&value.name)?,
+            Layout::Include => String::new(),
+        };
         let mut targets = Vec::new();
         let mut link_libraries = Vec::new();
-        if !sources.is_empty() {
+        let lib_sources: Vec<String> = sources
+            .into_iter()
+            .filter(SourceFile::is_compiled)
+            .map(|s| s.name)
+            .collect();
+        if value.src.library_kind == Some(LibraryKind::Interface)
+            && let Some(source) = lib_sources.first()
+        {
+            return Err(Error::InterfaceSource {
+                library: format!("{}_lib", value.name),
+                file: source.clone(),
+            });
+        }
+        // Header-only libraries have no sources but are still needed for their include
+        // directories.
+        if !lib_sources.is_empty() || value.src.library_kind == Some(LibraryKind::Interface) {
             let name = format!("{name}_lib", name = value.name);
             link_libraries.push(name.clone());
             targets.push(Target::Lib {
                 name,
-                sources: sources.into_iter().map(|s| s.name).collect(),
+                sources: lib_sources,
+                kind: value.src.library_kind,
+                public_headers: (value.src.layout == Layout::Include).then_some(INCLUDE_DIR),
+                link_libraries: value.src.libraries.clone(),
+                export: export.clone().map(|set| LibExport {
+                    set,
+                    headers: headers.iter().map(|h| h.name.clone()).collect(),
+                    include_dir: include_dir.clone(),
+                }),
+                package: value.packaging.is_some(),
+                config_header: value.config_header.is_some(),
+            });
+        }
+
+        let assets = load_assets(value)?;
+        let assets_target = (!assets.is_empty()).then(|| format!("{}_assets", value.name));
+        let install = export.is_some() || value.packaging.is_some();
+        let config_header = value.config_header.is_some() && link_libraries.is_empty();
+        if let Some(main_file) = main_file {
+            // The libraries of the project library are linked through it.
+            let libraries = if link_libraries.is_empty() { &value.src.libraries } else { &link_libraries };
+            targets.push(Target::Exe {
+                name: value.name.clone(),
+                sources: vec![main_file.name],
+                link_libraries: libraries.clone(),
+                assets_target: assets_target.clone(),
+                install,
+                config_header,
+            });
+        }
+
+        for (name, executable) in &value.src.executables {
+            let main_file = SourceFile::load_one(executable.main_file.name.clone(), &executable.main_file.source, value)?;
+            let exe_sources = load_all(&executable.sources, value)?;
+
+            let sources = std::iter::once(&main_file)
+                .chain(exe_sources.iter().filter(|s| s.is_compiled()))
+                .map(|s| s.name.clone())
+                .collect();
+            targets.push(Target::Exe {
+                name: name.clone(),
+                sources,
+                link_libraries: link_libraries.iter().chain(&executable.libraries).cloned().collect(),
+                assets_target: assets_target.clone(),
+                install,
+                config_header,
             });
+
+            files.push(main_file);
+            files.extend(exe_sources);
+        }
+
+        let libraries = CmakeLibrary::from_project(value)?;
+        let mut pkg_config = PkgConfig::from_project(value);
+        if let Some(pkg_config) = &mut pkg_config {
+            for target in &targets {
+                if let Target::Lib { name, kind, .. } = target {
+                    pkg_config.add_library(name, *kind, &include_dir);
+                }
+            }
+            for library in libraries.iter().rev() {
+                pkg_config.add_library(&library.name, library.kind, &library.name);
+            }
+            if value.config_header.is_some() {
+                // The version header is included as `<name/version.hpp>`.
+                pkg_config.add_include_dir("");
+            }
         }
-        link_libraries.extend(value.src.libraries.clone());
 
-        targets.push(Target::Exe {
-            name: value.name.clone(),
-            sources: vec![main_file.name],
-            link_libraries,
+        let executable = targets.iter().find_map(|t| match t {
+            Target::Exe { name, .. } => Some(name.clone()),
+            Target::Lib { .. } => None,
+        });
+        let copy_assets = assets_target.zip(executable).map(|(target, executable)| CopyAssets {
+            target,
+            executable,
+            files: assets.iter().map(|a| a.name.clone()).collect(),
+            install,
         });
 
         Ok(Self {
-            dir: value.src.dir.clone(),
+            dir: normalize_name(&value.src.dir)?,
             files,
+            public_headers,
+            libraries,
             targets,
+            assets,
+            copy_assets,
+            pkg_config,
+        })
+    }
+}
+
+impl CmakeSrc {
+    /// Name of the project library, if one is generated.
+    pub fn library(&self) -> Option<&str> {
+        self.targets.iter().find_map(|t| match t {
+            Target::Lib { name, .. } => Some(name.as_str()),
+            Target::Exe { .. } => None,
         })
     }
 }
 
+/// Loads the runtime assets, named relative to the project root.
+fn load_assets(project: &Project) -> Result<Vec<SourceFile>> {
+    let mut assets = Vec::new();
+    for (name, source) in &project.src.assets {
+        let name = format!("{ASSETS_DIR}/{}", normalize_name(name)?);
+        assets.extend(SourceFile::load(name, source, project)?);
+    }
+
+    Ok(assets)
+}
+
+fn load_all(sources: &IndexMap<String, crate::project::SourceFile>, project: &Project) -> Result<Vec<SourceFile>> {
+    let mut files = Vec::new();
+    for (name, source) in sources {
+        files.extend(SourceFile::load(name.clone(), source, project)?);
+    }
+
+    Ok(files)
+}
+
 impl TryFrom<CmakeSrc> for SourceNode {
-    type Error = anyhow::Error;
+    type Error = fmt::Error;
 
     fn try_from(value: CmakeSrc) -> Result<Self, Self::Error> {
         let mut cmakelists = String::new();
-        write_src(&mut cmakelists, &value.targets)?;
+        let subdirectories: Vec<&str> = value.libraries.iter().map(|l| l.dir.as_str()).collect();
+        write_src(&mut cmakelists, &subdirectories, &value.targets)?;
+        if let Some(copy_assets) = &value.copy_assets {
+            copy_assets.write(&mut cmakelists)?;
+        }
+        if let Some(pkg_config) = &value.pkg_config {
+            pkg_config.write(&mut cmakelists)?;
+        }
         trim_newline(&mut cmakelists);
         let mut children = Vec::new();
         children.push(SourceNode::File {
             name: "CMakeLists.txt".into(),
             contents: cmakelists.into_bytes(),
+            mode: None,
         });
 
+        if let Some(pkg_config) = &value.pkg_config {
+            SourceNode::insert(&mut children, pkg_config.node()?);
+        }
+
         for file in value.files {
-            children.push(file.into());
+            file.insert_into(&mut children);
+        }
+
+        for library in value.libraries {
+            SourceNode::insert(&mut children, library.into_node()?);
         }
 
         Ok(SourceNode::Directory { name: value.dir.into(), children })
     }
 }
 
-fn write_src(sb: &mut String, targets: &Vec<Target>) -> anyhow::Result<()> {
+fn write_src(sb: &mut String, subdirectories: &[&str], targets: &Vec<Target>) -> fmt::Result {
     writeln!(sb, "set(CMAKE_RUNTIME_OUTPUT_DIRECTORY ${{CMAKE_BINARY_DIR}})")?;
     writeln!(sb)?;
 
+    if !subdirectories.is_empty() {
+        for dir in subdirectories {
+            writeln!(sb, "add_subdirectory({dir})")?;
+        }
+        writeln!(sb)?;
+    }
+
     for target in targets {
         match target {
-            Target::Exe { name, sources, link_libraries } => {
+            Target::Exe { name, sources, link_libraries, assets_target, install, config_header } => {
                 writeln!(sb, "add_executable({name}")?;
                 for source in sources {
                     writeln!(sb, "    {source}")?;
@@ -110,6 +325,11 @@ fn write_src(sb: &mut String, targets: &Vec<Target>) -> anyhow::Result<()> {
                 writeln!(sb, ")")?;
                 writeln!(sb)?;
 
+                if *config_header {
+                    writeln!(sb, "target_include_directories({name} PRIVATE {GENERATED_INCLUDE_DIR})")?;
+                    writeln!(sb)?;
+                }
+
                 if !link_libraries.is_empty() {
                     writeln!(sb, "target_link_libraries({name}")?;
                     for lib in link_libraries {
@@ -118,16 +338,36 @@ fn write_src(sb: &mut String, targets: &Vec<Target>) -> anyhow::Result<()> {
                     writeln!(sb, ")")?;
                     writeln!(sb)?;
                 }
+
+                if let Some(assets_target) = assets_target {
+                    writeln!(sb, "add_dependencies({name} {assets_target})")?;
+                    writeln!(sb)?;
+                }
+
+                if *install {
+                    writeln!(sb, "install(TARGETS {name})")?;
+                    writeln!(sb)?;
+                }
             },
-            Target::Lib { sources, name } => {
-                writeln!(sb, "add_library({name}")?;
-                for source in sources {
-                    writeln!(sb, "    {source}")?;
+            Target::Lib { sources, name, kind, public_headers, link_libraries, export, package, config_header } => {
+                let sources: Vec<&str> = sources.iter().map(String::as_str).collect();
+                LibraryTarget {
+                    name,
+                    kind: *kind,
+                    sources: &sources,
+                    public_headers: *public_headers,
+                    public_dependencies: link_libraries,
+                    private_dependencies: &[],
+                    export: export.as_ref().map(|export| Export {
+                        set: &export.set,
+                        name: LIBRARY_EXPORT_NAME,
+                        headers: export.headers.iter().map(String::as_str).collect(),
+                        include_dir: &export.include_dir,
+                    }),
+                    package: *package,
+                    config_header: *config_header,
                 }
-                writeln!(sb, ")")?;
-                writeln!(sb)?;
-                writeln!(sb, "target_include_directories({name} PUBLIC .)")?;
-                writeln!(sb)?;
+                .write(sb)?;
             },
         }
     }
@@ -135,3 +375,30 @@ fn write_src(sb: &mut String, targets: &Vec<Target>) -> anyhow::Result<()> {
     Ok(())
 }
 
+impl CopyAssets {
+    /// Writes the custom target copying the assets. A stamp file records the last copy, so
+    /// that the copy only runs again when one of the asset files changes.
+    fn write(&self, sb: &mut String) -> fmt::Result {
+        let stamp = format!("${{CMAKE_CURRENT_BINARY_DIR}}/{ASSETS_DIR}.stamp");
+        writeln!(sb, "add_custom_command(")?;
+        writeln!(sb, "    OUTPUT {stamp}")?;
+        writeln!(sb, "    COMMAND ${{CMAKE_COMMAND}} -E copy_directory")?;
+        writeln!(sb, "        ${{PROJECT_SOURCE_DIR}}/{ASSETS_DIR}")?;
+        writeln!(sb, "        $<TARGET_FILE_DIR:{}>/{ASSETS_DIR}", self.executable)?;
+        writeln!(sb, "    COMMAND ${{CMAKE_COMMAND}} -E touch {stamp}")?;
+        writeln!(sb, "    DEPENDS")?;
+        for file in &self.files {
+            writeln!(sb, "        ${{PROJECT_SOURCE_DIR}}/{file}")?;
+        }
+        writeln!(sb, ")")?;
+        writeln!(sb, "add_custom_target({} DEPENDS {stamp})", self.target)?;
+        writeln!(sb)?;
+
+        if self.install {
+            writeln!(sb, "install(DIRECTORY ${{PROJECT_SOURCE_DIR}}/{ASSETS_DIR} DESTINATION ${{CMAKE_INSTALL_BINDIR}})")?;
+            writeln!(sb)?;
+        }
+
+        Ok(())
+    }
+}
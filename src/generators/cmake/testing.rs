@@ -3,7 +3,9 @@ use std::fmt::{self, Write as _};
 
 use indoc::writedoc;
 
+use crate::Result;
 use crate::project::Project;
+use crate::tree::normalize_name;
 use crate::tree::SourceNode;
 
 use super::file::SourceFile;
@@ -11,13 +13,15 @@ use super::file::SourceFile;
 pub struct Testing {
     pub dir: PathBuf,
     pub project_name: String,
+    /// Project library the tests are linked against.
+    pub library: Option<String>,
     #[allow(unused, reason = "Supporting only GoogleTest for now")]
     pub framework: crate::project::TestingFramework,
     pub files: Vec<SourceFile>,
 }
 
 impl Testing {
-    pub fn from_project(project: &Project) -> std::io::Result<Option<Self>> {
+    pub fn from_project(project: &Project, library: Option<&str>) -> Result<Option<Self>> {
         project
             .testing
             .as_ref()
@@ -25,13 +29,17 @@ impl Testing {
                 let files = t
                     .sources
                     .iter()
-                    .map(|(name, source)| SourceFile::load(name.clone(), source))
-                    .collect::<Result<_, _>>()?;
+                    .map(|(name, source)| SourceFile::load(name.clone(), source, project))
+                    .collect::<Result<Vec<_>>>()?
+                    .into_iter()
+                    .flatten()
+                    .collect();
 
                 Ok(Testing {
-                    dir: t.dir.as_str().into(),
+                    dir: normalize_name(&t.dir)?.into(),
                     framework: t.framework,
                     project_name: project.name.clone(),
+                    library: library.map(str::to_owned),
                     files,
                 })
             })
@@ -39,23 +47,29 @@ impl Testing {
     }
 
     pub fn into_node(self) -> Result<SourceNode, fmt::Error> {
-        let sources = self.files.iter().map(|s| &s.name).cloned().collect();
+        let sources = self
+            .files
+            .iter()
+            .filter(|s| s.is_compiled())
+            .map(|s| &s.name)
+            .cloned()
+            .collect();
         let mut cmakelists = String::new();
-        write_tests(&mut cmakelists, &self.project_name, &sources)?;
+        write_tests(&mut cmakelists, &self.project_name, self.library.as_deref(), &sources)?;
 
         let mut children = vec![
-            SourceNode::File { name: "CMakeLists.txt".into(), contents: cmakelists.into_bytes() },
+            SourceNode::File { name: "CMakeLists.txt".into(), contents: cmakelists.into_bytes(), mode: None },
         ];
 
         for file in self.files {
-            children.push(file.into());
+            file.insert_into(&mut children);
         }
 
         Ok(SourceNode::Directory { name: self.dir, children })
     }
 }
 
-fn write_tests(sb: &mut String, name: &str, sources: &Vec<String>) -> fmt::Result {
+fn write_tests(sb: &mut String, name: &str, library: Option<&str>, sources: &Vec<String>) -> fmt::Result {
     writeln!(sb, "add_executable({name}_test")?;
     for source in sources {
         writeln!(sb, "    {source}")?;
@@ -63,11 +77,13 @@ fn write_tests(sb: &mut String, name: &str, sources: &Vec<String>) -> fmt::Resul
     writeln!(sb, ")")?;
     writeln!(sb)?;
 
+    writeln!(sb, "target_link_libraries({name}_test")?;
+    if let Some(library) = library {
+        writeln!(sb, "    {library}")?;
+    }
     writedoc!(
         sb,
         r"
-        target_link_libraries({name}_test
-            {name}_lib
             gtest
             gtest_main
         )
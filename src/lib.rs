@@ -0,0 +1,30 @@
+//! Generation of C++ project skeletons from declarative descriptions.
+//!
+//! A [`Project`](project::Project) is loaded from a YAML, JSON, TOML or XML description, turned
+//! into a [`SourceTree`](tree::SourceTree) by a [`Generator`](generators::Generator) and rendered
+//! to disk or into any other [`Sink`](sink::Sink):
+//!
+//! ```no_run
+//! use cpp_project_init::{
+//!     generators::{CmakeProject, Generator},
+//!     project::Project,
+//! };
+//!
+//! # fn main() -> cpp_project_init::Result<()> {
+//! let project = Project::load("templates/raylib")?;
+//! let tree = CmakeProject::try_from(project)?.into_tree()?;
+//! tree.render("out".as_ref(), false)?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod error;
+pub mod generators;
+pub mod project;
+pub mod sink;
+pub mod tree;
+pub mod validate;
+
+mod strings;
+
+pub use error::{Error, Result};
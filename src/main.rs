@@ -5,7 +5,7 @@
 //
 // TODO: Unit tests
 //         - [ ] Collect test coverage
-//         - [ ] Unit tests for Cmake generators
+//         - [x] Unit tests for Cmake generators
 //
 // TODO: Init command
 //
@@ -31,45 +31,146 @@
 //
 // TODO: GUI
 //         - [ ] Decide the framework
-//         - [ ] Split project to lib and cli executable
+//         - [x] Split project to lib and cli executable
 //         - [ ] GUI executable
-use std::fs;
+use std::{
+    fs::File,
+    path::Path,
+    process::ExitCode,
+};
 
 use clap::Parser;
 use cli::Cli;
-use generators::{CmakeProject, Generator};
-use project::Project;
+use cpp_project_init::{
+    Error, Result,
+    generators::{CmakeProject, Generator},
+    project::Project,
+    sink::{TarSink, ZipSink},
+    tree::{SourceNode, SourceTree},
+    validate,
+};
+use flate2::{Compression, write::GzEncoder};
 
-pub(crate) mod cli;
-pub(crate) mod project;
-pub(crate) mod generators;
-pub(crate) mod tree;
-pub(crate) mod strings;
+mod cli;
 
-fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+/// Exit codes for each kind of failure. Code 2 is used by `clap` for usage errors.
+fn exit_code(error: &Error) -> u8 {
+    match error {
+        Error::UnsupportedArchive(_) => 2,
+        Error::DescriptionNotFound(_) => 3,
+        Error::AmbiguousDescription { .. } => 4,
+        Error::UnsupportedFormat(_)
+        | Error::ReadDescription { .. }
+        | Error::ParseDescription { .. }
+        | Error::InvalidDescription(_)
+        | Error::DependencyCycle(_)
+        | Error::SymlinkMode { .. }
+        | Error::InterfaceSource { .. }
+        | Error::ExportedPackage { .. }
+        | Error::ReservedLibraryName(_)
+        | Error::InvalidVersion(_)
+        | Error::InvalidOptionName(_)
+        | Error::MissingLicenseFile(_) => 5,
+        Error::SourceFile { .. }
+        | Error::InvalidPattern { .. }
+        | Error::InvalidBase64 { .. }
+        | Error::NotASingleFile { .. } => 6,
+        Error::UnsafePath(_) => 7,
+        Error::Write { .. } => 8,
+        Error::Format(_) => 1,
+    }
+}
 
+fn run(cli: Cli) -> Result<()> {
     match cli.command {
-        cli::Command::New { out } => {
+        cli::Command::New { out, archive } => {
             let project = if let Some(from) = cli.generate.from {
                 Project::load(from)?
             } else {
                 Project::default()
             };
 
-            if cli.generate.force && fs::exists(&out).unwrap_or(false) {
-                fs::remove_dir_all(&out)?;
+            if let Some(archive) = archive {
+                let name = project.name.clone();
+                let generated = match cli.generate.build_system {
+                    cli::BuildSystem::Cmake => CmakeProject::try_from(project)?.into_tree()?,
+                };
+
+                let mut tree = SourceTree::default();
+                tree.insert(SourceNode::Directory {
+                    name: name.into(),
+                    children: generated.children,
+                });
+                return write_archive(&tree, &archive);
             }
-            fs::create_dir_all(&out)?;
 
+            let out = out.expect("either an output directory or an archive is required");
             match cli.generate.build_system {
-                cli::BuildSystem::Cmake => CmakeProject::generate(project, &out)?,
+                cli::BuildSystem::Cmake => CmakeProject::generate(project, &out, cli.generate.force)?,
             }
         }
 
         cli::Command::Init {} => {
             todo!()
         }
+
+        cli::Command::Validate { description } => {
+            let report = validate::validate(description)?;
+            for diagnostic in &report.diagnostics {
+                eprintln!("{diagnostic}");
+                eprintln!();
+            }
+
+            if report.has_errors() {
+                return Err(Error::InvalidDescription(report.file));
+            }
+        }
+
+        cli::Command::Schema => {
+            let schema = schemars::schema_for!(Project);
+            let schema = serde_json::to_string_pretty(&schema).expect("schema is valid JSON");
+            println!("{schema}");
+        }
+    }
+
+    Ok(())
+}
+
+fn write_archive(tree: &SourceTree, path: &Path) -> Result<()> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let write_error = |source| Error::Write {
+        path: path.to_path_buf(),
+        source,
+    };
+    let create = || File::create(path).map_err(write_error);
+
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        let mut sink = TarSink::new(GzEncoder::new(create()?, Compression::default()));
+        tree.write(&mut sink)?;
+        sink.finish().and_then(GzEncoder::finish).map_err(write_error)?;
+    } else if name.ends_with(".tar") {
+        let mut sink = TarSink::new(create()?);
+        tree.write(&mut sink)?;
+        sink.finish().map_err(write_error)?;
+    } else if name.ends_with(".zip") {
+        let mut sink = ZipSink::new(create()?);
+        tree.write(&mut sink)?;
+        sink.finish().map_err(write_error)?;
+    } else {
+        return Err(Error::UnsupportedArchive(path.to_path_buf()));
     }
 
     Ok(())
@@ -1,35 +1,118 @@
 use std::{
+    borrow::Cow,
     collections::HashMap,
     ffi::OsStr,
     fs,
     path::{Path, PathBuf},
 };
 
-use anyhow::bail;
-use serde::{Deserialize, Serialize};
+use base64::Engine;
+use indexmap::IndexMap;
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::{Error, Result};
+
+/// Description of a C++ project to generate.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Project {
+    /// Project name, also used for the executable and library targets.
     #[serde(default = "Project::default_name")]
     pub name: String,
 
+    /// What the project builds.
+    #[serde(default)]
+    pub kind: ProjectKind,
+
+    /// Project version in the `major.minor.patch` form. Installed projects use `0.1.0` when
+    /// omitted.
+    #[serde(default)]
+    pub version: Option<String>,
+
+    /// One-line summary of the project.
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// Project website.
+    #[serde(default)]
+    pub homepage: Option<String>,
+
+    /// Project authors, e.g. `Jane Doe <jane@example.com>`.
+    #[serde(default)]
+    pub authors: Vec<String>,
+
+    /// License of the project as an SPDX expression, e.g. `MIT`.
+    #[serde(default)]
+    pub license: Option<String>,
+
+    /// Test suite configuration. Tests are not generated when omitted.
     #[serde(default)]
     pub testing: Option<Testing>,
 
+    /// Main source directory.
     #[serde(default)]
     pub src: SrcDir,
 
+    /// Internal libraries, each built from its own subdirectory of the sources directory and
+    /// keyed by target name.
+    #[serde(default)]
+    pub libraries: IndexMap<String, Library>,
+
+    /// External packages fetched at configure time, keyed by package name.
     #[serde(default)]
-    pub packages: HashMap<String, PackageSource>,
+    pub packages: IndexMap<String, PackageSource>,
+
+    /// Install rules and a CMake package for downstream projects. Nothing is installed when
+    /// omitted. The project library is exported as `<namespace>::lib`, so no internal library
+    /// can be named `lib` then.
+    #[serde(default)]
+    pub install: Option<Install>,
+
+    /// Generated `<name>/version.hpp` header with the project version, the git commit and
+    /// compile-time options. Not generated when omitted.
+    ///
+    /// The header defines `<NAME>_VERSION`, `<NAME>_VERSION_MAJOR`, `<NAME>_VERSION_MINOR`,
+    /// `<NAME>_VERSION_PATCH` and `<NAME>_GIT_COMMIT`, where `<NAME>` is the project name in
+    /// upper case with other characters replaced by `_`. The values come from CMake's
+    /// `PROJECT_VERSION*` variables, but the macros are prefixed so that the headers of several
+    /// projects can be included together.
+    #[serde(default)]
+    pub config_header: Option<ConfigHeader>,
+
+    /// Binary packages built with CPack. Executables, the libraries they load at runtime and
+    /// the assets are installed into the packages even without an `install` section.
+    #[serde(default)]
+    pub packaging: Option<Packaging>,
+
+    /// Extra files placed relative to the project root, such as helper scripts or git hooks.
+    #[serde(default)]
+    pub files: IndexMap<String, ProjectFile>,
+
+    /// Directory relative to which source file paths are resolved.
+    #[serde(skip)]
+    pub base_dir: PathBuf,
 }
 
 impl Default for Project {
     fn default() -> Self {
         Self {
             name: Self::default_name(),
+            kind: Default::default(),
+            version: Default::default(),
+            description: Default::default(),
+            homepage: Default::default(),
+            authors: Default::default(),
+            license: Default::default(),
             testing: Default::default(),
             src: Default::default(),
+            libraries: Default::default(),
             packages: Default::default(),
+            install: Default::default(),
+            config_header: Default::default(),
+            packaging: Default::default(),
+            files: Default::default(),
+            base_dir: Default::default(),
         }
     }
 }
@@ -39,127 +122,634 @@ impl Project {
         "app".into()
     }
 
-    pub fn load(input: impl AsRef<Path>) -> anyhow::Result<Self> {
-        let input = input.as_ref();
-
-        let not_found =
-            || anyhow::anyhow!("Project description file not found in the description directory");
-
-        let file = if !input.is_dir() {
-            input.to_path_buf()
-        } else {
-            let files: Vec<PathBuf> = fs::read_dir(input)?
-                .filter_map(|e| Some(e.ok()?.path()))
-                .filter(|p| {
-                    if let Some(f) = p.file_stem() {
-                        f == OsStr::new("project")
-                    } else {
-                        false
-                    }
-                })
-                .collect();
-
-            match files.as_slice() {
-                [] => bail!(not_found()),
-                [file] => file.clone(),
-                _ => bail!("More than one description file found in the description directory"),
+    pub fn load(input: impl AsRef<Path>) -> Result<Self> {
+        let file = Self::find_description(input.as_ref())?;
+        let format = Format::from_path(&file).ok_or_else(|| Error::UnsupportedFormat(file.clone()))?;
+        let contents = fs::read_to_string(&file).map_err(|source| Error::ReadDescription {
+            path: file.clone(),
+            source,
+        })?;
+
+        let project = Self::parse(&file, &contents, format)
+            .map_err(|source| Error::ParseDescription { path: file, source })?;
+        project.library_order()?;
+
+        Ok(project)
+    }
+
+    pub fn parse(file: &Path, contents: &str, format: Format) -> Result<Self, ParseError> {
+        let mut project: Self = format.parse(contents)?;
+        project.base_dir = file.parent().unwrap_or(Path::new("")).to_path_buf();
+
+        Ok(project)
+    }
+
+    /// Packages that installed libraries would put into the install export, as pairs of
+    /// library and package. Packages are fetched at configure time and never installed, so
+    /// they cannot be exported: this covers public dependencies, and private ones too unless
+    /// the library is shared, since static libraries export them as link dependencies.
+    pub fn exported_packages(&self) -> Vec<(String, String)> {
+        let mut exported = Vec::new();
+        if self.install.is_none() {
+            return exported;
+        }
+
+        let project_library = self.has_project_library().then(|| {
+            let name = format!("{}_lib", self.name);
+            (name, self.src.library_kind, self.src.libraries.as_slice(), [].as_slice())
+        });
+        let libraries = self.libraries.iter().map(|(name, library)| {
+            let dependencies = (library.public_dependencies.as_slice(), library.private_dependencies.as_slice());
+            (name.clone(), library.kind, dependencies.0, dependencies.1)
+        });
+        for (library, kind, public, private) in project_library.into_iter().chain(libraries) {
+            let private = private.iter().filter(|_| kind != Some(LibraryKind::Shared));
+            for package in public.iter().chain(private) {
+                if self.packages.contains_key(package) {
+                    exported.push((library.clone(), package.clone()));
+                }
             }
-        };
+        }
+
+        exported
+    }
+
+    /// Whether the project library may be generated: it is built from the compiled sources of
+    /// `src`, which directories and patterns may provide, or is header-only.
+    pub fn has_project_library(&self) -> bool {
+        self.src.library_kind == Some(LibraryKind::Interface)
+            || self.src.sources.iter().any(|(name, source)| {
+                is_compiled(name) || matches!(source, SourceFile::Dir { .. } | SourceFile::Glob { .. })
+            })
+    }
+
+    /// Orders the internal libraries so that every library comes after the internal libraries
+    /// it depends on, failing on dependency cycles.
+    pub fn library_order(&self) -> Result<Vec<&str>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            Visiting,
+            Done,
+        }
 
-        let ext = file
-            .extension()
-            .ok_or_else(not_found)?
-            .to_str()
-            .ok_or_else(not_found)?;
+        fn visit<'a>(
+            project: &'a Project,
+            name: &'a str,
+            states: &mut HashMap<&'a str, State>,
+            stack: &mut Vec<&'a str>,
+            order: &mut Vec<&'a str>,
+        ) -> Result<()> {
+            let Some(library) = project.libraries.get(name) else {
+                return Ok(());
+            };
+
+            match states.get(name) {
+                Some(State::Done) => return Ok(()),
+                Some(State::Visiting) => {
+                    let start = stack.iter().position(|n| *n == name).unwrap_or_default();
+                    let mut cycle: Vec<String> = stack[start..].iter().map(|n| n.to_string()).collect();
+                    cycle.push(name.to_owned());
+                    return Err(Error::DependencyCycle(cycle));
+                }
+                None => {}
+            }
+
+            states.insert(name, State::Visiting);
+            stack.push(name);
+            for dependency in library.dependencies() {
+                visit(project, dependency, states, stack, order)?;
+            }
+            stack.pop();
+            states.insert(name, State::Done);
+            order.push(name);
+
+            Ok(())
+        }
+
+        let mut states = HashMap::new();
+        let mut order = Vec::new();
+        for name in self.libraries.keys() {
+            visit(self, name, &mut states, &mut Vec::new(), &mut order)?;
+        }
+
+        Ok(order)
+    }
+
+    pub fn resolve_path(&self, path: &str) -> PathBuf {
+        match path.strip_prefix(CWD_PREFIX) {
+            Some(path) => PathBuf::from(path),
+            None => self.base_dir.join(path),
+        }
+    }
+
+    pub fn find_description(input: &Path) -> Result<PathBuf> {
+        if !input.exists() {
+            return Err(Error::DescriptionNotFound(input.to_path_buf()));
+        }
 
-        let contents = fs::read_to_string(&file)?;
+        if !input.is_dir() {
+            return Ok(input.to_path_buf());
+        }
 
-        let project = match ext {
-            "yaml" | "yml" => serde_yml::from_str(&contents)?,
-            "json" => serde_json::from_str(&contents)?,
-            "toml" => toml::from_str(&contents)?,
-            "xml" => serde_xml_rs::from_str(&contents)?,
-            _ => bail!(not_found()),
+        let read_error = |source| Error::ReadDescription {
+            path: input.to_path_buf(),
+            source,
         };
+        let files: Vec<PathBuf> = fs::read_dir(input)
+            .map_err(read_error)?
+            .filter_map(|e| Some(e.ok()?.path()))
+            .filter(|p| {
+                if let Some(f) = p.file_stem() {
+                    f == OsStr::new("project")
+                } else {
+                    false
+                }
+            })
+            .collect();
+
+        match files.as_slice() {
+            [] => Err(Error::DescriptionNotFound(input.to_path_buf())),
+            [file] => Ok(file.clone()),
+            _ => Err(Error::AmbiguousDescription {
+                path: input.to_path_buf(),
+                candidates: files,
+            }),
+        }
+    }
+}
 
-        Ok(project)
+/// What a project builds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectKind {
+    /// An executable built from the main file and linked against the project library.
+    #[default]
+    Executable,
+    /// Only the project library. The main file is not generated.
+    Library,
+}
+
+/// Installation of the generated targets and of a package config for `find_package`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Install {
+    /// Namespace of the exported targets, e.g. `Name` for `Name::lib`. Defaults to the
+    /// project name.
+    #[serde(default)]
+    pub namespace: Option<String>,
+
+    /// Which requested versions the installed package satisfies.
+    #[serde(default)]
+    pub compatibility: Compatibility,
+
+    /// Also install a pkg-config file for consumers that do not use CMake.
+    #[serde(default)]
+    pub pkg_config: bool,
+}
+
+/// Header configured at build time with the project version and options.
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigHeader {
+    /// Compile-time options, keyed by the name of both the CMake option and the macro,
+    /// which is defined to `0` or `1`.
+    #[serde(default)]
+    pub options: IndexMap<String, ConfigOption>,
+}
+
+/// Boolean option toggled when configuring the project.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigOption {
+    /// Help text shown by CMake.
+    #[serde(default)]
+    pub description: String,
+
+    /// Whether the option is enabled by default.
+    #[serde(default)]
+    pub default: bool,
+}
+
+/// CPack configuration for building binary packages.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Packaging {
+    /// Package formats to build.
+    #[serde(default = "Packaging::default_generators")]
+    pub generators: Vec<PackageGenerator>,
+
+    /// Package maintainer, e.g. `Jane Doe <jane@example.com>`. Defaults to the first project
+    /// author. Required for Debian packages.
+    #[serde(default)]
+    pub maintainer: Option<String>,
+
+    /// License file shown by installers, relative to the project root. It has to be one of the
+    /// generated files, e.g. declared in `files`.
+    #[serde(default)]
+    pub license_file: Option<String>,
+
+    /// Package summary. Defaults to the project description.
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// Packages the generated packages depend on, as named by the target distributions.
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+impl Packaging {
+    fn default_generators() -> Vec<PackageGenerator> {
+        vec![PackageGenerator::Tgz]
+    }
+}
+
+/// Package format built by CPack.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageGenerator {
+    /// Gzipped tarball.
+    Tgz,
+    /// Debian package.
+    Deb,
+    /// RPM package.
+    Rpm,
+}
+
+impl PackageGenerator {
+    pub fn as_cmake(self) -> &'static str {
+        match self {
+            PackageGenerator::Tgz => "TGZ",
+            PackageGenerator::Deb => "DEB",
+            PackageGenerator::Rpm => "RPM",
+        }
+    }
+}
+
+/// Version compatibility of an installed package.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Compatibility {
+    /// Any older version may be requested.
+    AnyNewerVersion,
+    /// Older versions with the same major version may be requested.
+    #[default]
+    SameMajorVersion,
+    /// Older versions with the same major and minor version may be requested.
+    SameMinorVersion,
+    /// Only the exact version may be requested.
+    ExactVersion,
+}
+
+impl Compatibility {
+    pub fn as_cmake(self) -> &'static str {
+        match self {
+            Compatibility::AnyNewerVersion => "AnyNewerVersion",
+            Compatibility::SameMajorVersion => "SameMajorVersion",
+            Compatibility::SameMinorVersion => "SameMinorVersion",
+            Compatibility::ExactVersion => "ExactVersion",
+        }
+    }
+}
+
+/// Prefix marking a source path as relative to the current working directory instead of the
+/// description file.
+const CWD_PREFIX: &str = "cwd:";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Yaml,
+    Json,
+    Toml,
+    Xml,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "yaml" | "yml" => Some(Self::Yaml),
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            "xml" => Some(Self::Xml),
+            _ => None,
+        }
+    }
+
+    pub fn parse<T: DeserializeOwned>(self, contents: &str) -> Result<T, ParseError> {
+        Ok(match self {
+            Self::Yaml => serde_yml::from_str(contents)?,
+            Self::Json => serde_json::from_str(contents)?,
+            Self::Toml => toml::from_str(contents)?,
+            Self::Xml => serde_xml_rs::from_str(contents)?,
+        })
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, thiserror::Error)]
+pub enum ParseError {
+    #[error(transparent)]
+    Yaml(#[from] serde_yml::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+
+    #[error(transparent)]
+    Xml(#[from] serde_xml_rs::Error),
+}
+
+/// Test suite configuration.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Testing {
+    /// Directory containing the tests, relative to the project root.
     #[serde(default = "default_tests_dir")]
     pub dir: String,
 
+    /// Testing framework the tests are written for.
     #[serde(default)]
     pub framework: TestingFramework,
 
+    /// Test sources, keyed by file name.
     #[serde(default = "default_tests_sources")]
-    pub sources: HashMap<String, SourceFile>,
+    pub sources: IndexMap<String, SourceFile>,
 }
 
 fn default_tests_dir() -> String {
     "tests".into()
 }
 
-fn default_tests_sources() -> HashMap<String, SourceFile> {
-    HashMap::from_iter([(
+fn default_tests_sources() -> IndexMap<String, SourceFile> {
+    IndexMap::from_iter([(
         "test_greet.cpp".into(),
         SourceFile::Contents(include_str!("default/sources/test_greet_gtest.cpp").into()),
     )])
 }
 
-#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+/// Supported testing frameworks.
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize, JsonSchema)]
 pub enum TestingFramework {
     #[default]
     GoogleTest,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Main source directory configuration.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct SrcDir {
+    /// Directory containing the sources, relative to the project root.
     #[serde(default = "default_src_dir")]
     pub dir: String,
 
+    /// Source file containing the `main` function of the executable. Ignored for library
+    /// projects.
     #[serde(default)]
     pub main_file: MainFile,
 
+    /// Library sources, keyed by file name.
+    #[serde(default)]
+    pub sources: IndexMap<String, SourceFile>,
+
+    /// Public library headers, keyed by file name.
     #[serde(default)]
-    pub sources: HashMap<String, SourceFile>,
+    pub headers: IndexMap<String, SourceFile>,
 
+    /// Headers used only by the library and executable sources, keyed by file name. They are
+    /// always placed next to the sources.
     #[serde(default)]
-    pub headers: HashMap<String, SourceFile>,
+    pub private_headers: IndexMap<String, SourceFile>,
 
+    /// Where the public headers are placed.
+    #[serde(default)]
+    pub layout: Layout,
+
+    /// Libraries the project library is linked against. Targets linking the project library
+    /// link them as well, and the executable links them directly when there is no project
+    /// library.
     #[serde(default)]
     pub libraries: Vec<String>,
+
+    /// Type of the project library built from `sources`. CMake decides from
+    /// `BUILD_SHARED_LIBS` when omitted.
+    #[serde(default)]
+    pub library_kind: Option<LibraryKind>,
+
+    /// Additional executables sharing the project library, keyed by target name.
+    #[serde(default)]
+    pub executables: IndexMap<String, Executable>,
+
+    /// Runtime resources, keyed by file name. They are placed in the `assets` directory and
+    /// copied next to the executables when building, again whenever one of them changes.
+    #[serde(default)]
+    pub assets: IndexMap<String, SourceFile>,
 }
 
 impl Default for SrcDir {
     fn default() -> Self {
         Self {
             dir: default_src_dir(),
-            sources: HashMap::from_iter([(
+            sources: IndexMap::from_iter([(
                 "lib.cpp".to_owned(),
                 SourceFile::Contents(include_str!("default/sources/lib.cpp").to_owned()),
             )]),
-            headers: HashMap::from_iter([(
+            headers: IndexMap::from_iter([(
                 "lib.hpp".to_owned(),
                 SourceFile::Contents(include_str!("default/sources/lib.hpp").to_owned()),
             )]),
             main_file: Default::default(),
+            private_headers: Default::default(),
+            layout: Default::default(),
             libraries: Default::default(),
+            library_kind: Default::default(),
+            executables: Default::default(),
+            assets: Default::default(),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Library built from its own subdirectory of the sources directory.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Library {
+    /// Subdirectory of the sources directory containing the library. Defaults to the library
+    /// name.
+    #[serde(default)]
+    pub dir: Option<String>,
+
+    /// Type of the library. CMake decides from `BUILD_SHARED_LIBS` when omitted.
+    #[serde(default)]
+    pub kind: Option<LibraryKind>,
+
+    /// Library sources, keyed by file name.
+    #[serde(default)]
+    pub sources: IndexMap<String, SourceFile>,
+
+    /// Library headers, keyed by file name.
+    #[serde(default)]
+    pub headers: IndexMap<String, SourceFile>,
+
+    /// Libraries used in the public headers. Targets linking this library link them as well.
+    #[serde(default)]
+    pub public_dependencies: Vec<String>,
+
+    /// Libraries used only by the implementation.
+    #[serde(default)]
+    pub private_dependencies: Vec<String>,
+}
+
+/// Placement of the public headers of the project library.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+pub enum Layout {
+    /// Public headers are placed next to the sources and included as `"lib.hpp"`.
+    #[default]
+    Flat,
+    /// Public headers are placed under `include/<project name>/` and included as
+    /// `<project name/lib.hpp>`.
+    Include,
+}
+
+/// Type of a generated library target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LibraryKind {
+    /// Static archive.
+    Static,
+    /// Shared library. Symbols are hidden by default and exported through the macros of a
+    /// generated `<name>_export.h` header.
+    Shared,
+    /// Object files linked directly into the targets using the library.
+    Object,
+    /// Header-only library without compiled sources.
+    Interface,
+}
+
+impl Library {
+    pub fn dependencies(&self) -> impl Iterator<Item = &str> {
+        self.public_dependencies
+            .iter()
+            .chain(&self.private_dependencies)
+            .map(String::as_str)
+    }
+}
+
+/// Executable built from the main source directory in addition to the main one.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Executable {
+    /// Source file containing the `main` function of the executable.
+    pub main_file: MainFile,
+
+    /// Sources compiled only into this executable, keyed by file name.
+    #[serde(default)]
+    pub sources: IndexMap<String, SourceFile>,
+
+    /// Libraries the executable is linked against, in addition to the project library.
+    #[serde(default)]
+    pub libraries: Vec<String>,
+}
+
+/// Where the contents of a generated file come from.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub enum SourceFile {
+    /// Copy the contents of the file at the given path. Relative paths are resolved against the
+    /// directory containing the description file, or against the current working directory
+    /// when prefixed with `cwd:`.
     Path(String),
+    /// Use the given text as the file contents.
     Contents(String),
+    /// Use the given base64 text, decoded, as the file contents. Whitespace is ignored, so
+    /// long values can be wrapped over several lines.
+    Base64(String),
+    /// Copy a whole directory, keeping its structure. The entry name is used as the directory
+    /// name.
+    Dir {
+        /// Directory path, resolved the same way as [`SourceFile::Path`].
+        path: String,
+    },
+    /// Copy every file matching a glob pattern into a directory named after the entry. Matches
+    /// keep their structure relative to the part of the pattern before the first wildcard.
+    Glob {
+        /// Glob pattern, resolved the same way as [`SourceFile::Path`].
+        pattern: String,
+    },
+    /// Create a symlink pointing to the given path, relative to the link itself. The target
+    /// has to stay inside the project directory.
+    Symlink(String),
+}
+
+const SOURCE_EXTENSIONS: &[&str] = &["c", "cc", "cpp", "cxx", "c++"];
+
+/// Whether a file with the given name is compiled rather than included.
+pub fn is_compiled(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| SOURCE_EXTENSIONS.contains(&e))
+}
+
+/// Decodes the contents of a [`SourceFile::Base64`] entry.
+pub fn decode_base64(text: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    let text: String = text.split_ascii_whitespace().collect();
+    base64::engine::general_purpose::STANDARD.decode(text)
+}
+
+/// File placed relative to the project root.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "RawProjectFile")]
+pub struct ProjectFile {
+    #[serde(flatten)]
+    pub source: SourceFile,
+
+    /// Unix permission bits applied to every generated file, e.g. `"755"` for scripts. Not
+    /// allowed for symlinks.
+    #[serde(default)]
+    pub mode: Option<FileMode>,
+}
+
+/// Unix permission bits, written as an octal string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct FileMode(pub u32);
+
+impl TryFrom<String> for FileMode {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match u32::from_str_radix(&value, 8) {
+            Ok(mode) if (3..=4).contains(&value.len()) && mode <= 0o7777 => Ok(Self(mode)),
+            _ => Err(format!("invalid file mode `{value}`, expected octal digits such as `755`")),
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl From<FileMode> for String {
+    fn from(value: FileMode) -> Self {
+        format!("{:o}", value.0)
+    }
+}
+
+impl JsonSchema for FileMode {
+    fn schema_name() -> Cow<'static, str> {
+        "FileMode".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Unix permission bits, written as an octal string.",
+            "type": "string",
+            "pattern": "^[0-7]{3,4}$",
+        })
+    }
+}
+
+/// Source file containing the `main` function.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(try_from = "RawMainFile")]
 pub struct MainFile {
+    /// File name of the main source.
     #[serde(default = "default_main_name")]
     pub name: String,
 
@@ -171,15 +761,130 @@ fn default_main_name() -> String {
     "main.cpp".into()
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
+// Serde does not support denying unknown fields next to a flattened field, so [`ProjectFile`]
+// and [`MainFile`] are read from structs listing the keys of every [`SourceFile`] variant
+// instead. Parsers then locate unknown keys, and the schema rejects them.
+
+/// [`ProjectFile`] as written in descriptions.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct RawProjectFile {
+    /// Copy the contents of the file at the given path.
+    path: Option<String>,
+    /// Use the given text as the file contents.
+    contents: Option<String>,
+    /// Use the given base64 text, decoded, as the file contents.
+    base64: Option<String>,
+    /// Copy a whole directory, keeping its structure.
+    dir: Option<DirSource>,
+    /// Copy every file matching a glob pattern.
+    glob: Option<GlobSource>,
+    /// Create a symlink pointing to the given path, relative to the link itself.
+    symlink: Option<String>,
+
+    /// Unix permission bits applied to every generated file, e.g. `"755"` for scripts. Not
+    /// allowed for symlinks.
+    #[serde(default)]
+    mode: Option<FileMode>,
+}
+
+/// [`MainFile`] as written in descriptions.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct RawMainFile {
+    /// File name of the main source.
+    #[serde(default = "default_main_name")]
+    name: String,
+
+    /// Copy the contents of the file at the given path.
+    path: Option<String>,
+    /// Use the given text as the file contents.
+    contents: Option<String>,
+    /// Use the given base64 text, decoded, as the file contents.
+    base64: Option<String>,
+    /// Copy a whole directory, keeping its structure.
+    dir: Option<DirSource>,
+    /// Copy every file matching a glob pattern.
+    glob: Option<GlobSource>,
+    /// Create a symlink pointing to the given path, relative to the link itself.
+    symlink: Option<String>,
+}
+
+/// Arguments of [`SourceFile::Dir`].
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct DirSource {
+    /// Directory path.
+    path: String,
+}
+
+/// Arguments of [`SourceFile::Glob`].
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct GlobSource {
+    /// Glob pattern.
+    pattern: String,
+}
+
+/// Returns the only source set among the keys of an entry.
+fn single_source(sources: [Option<SourceFile>; 6]) -> Result<SourceFile, String> {
+    let mut sources = sources.into_iter().flatten();
+    match (sources.next(), sources.next()) {
+        (Some(source), None) => Ok(source),
+        _ => Err("expected exactly one of `path`, `contents`, `base64`, `dir`, `glob` or `symlink`".to_owned()),
+    }
+}
+
+impl TryFrom<RawProjectFile> for ProjectFile {
+    type Error = String;
+
+    fn try_from(value: RawProjectFile) -> Result<Self, Self::Error> {
+        let source = single_source([
+            value.path.map(SourceFile::Path),
+            value.contents.map(SourceFile::Contents),
+            value.base64.map(SourceFile::Base64),
+            value.dir.map(|d| SourceFile::Dir { path: d.path }),
+            value.glob.map(|g| SourceFile::Glob { pattern: g.pattern }),
+            value.symlink.map(SourceFile::Symlink),
+        ])?;
+
+        Ok(Self { source, mode: value.mode })
+    }
+}
+
+impl TryFrom<RawMainFile> for MainFile {
+    type Error = String;
+
+    fn try_from(value: RawMainFile) -> Result<Self, Self::Error> {
+        let source = single_source([
+            value.path.map(SourceFile::Path),
+            value.contents.map(SourceFile::Contents),
+            value.base64.map(SourceFile::Base64),
+            value.dir.map(|d| SourceFile::Dir { path: d.path }),
+            value.glob.map(|g| SourceFile::Glob { pattern: g.pattern }),
+            value.symlink.map(SourceFile::Symlink),
+        ])?;
+
+        Ok(Self { name: value.name, source })
+    }
+}
+
+/// Where an external package is fetched from.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub enum PackageSource {
+    /// Download and extract an archive.
     Download {
+        /// Archive URL.
         url: String,
+        /// Expected archive hash in the `<algorithm>=<hash>` form.
         checksum: Option<String>,
     },
+    /// Clone a git repository.
     Git {
+        /// Repository URL.
         url: String,
+        /// Tag, branch or commit to check out.
         tag: Option<String>,
     },
 }
@@ -196,3 +901,65 @@ impl Default for MainFile {
 fn default_src_dir() -> String {
     "src".into()
 }
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    fn project(description: &str) -> Project {
+        Format::Toml.parse(description).unwrap()
+    }
+
+    #[test]
+    fn libraries_are_ordered_after_their_dependencies() {
+        let project = project(indoc! {r#"
+            [libraries]
+            ui = { public_dependencies = ["net"] }
+            net = { public_dependencies = ["core"], private_dependencies = ["zlib"] }
+            core = {}
+        "#});
+
+        assert_eq!(project.library_order().unwrap(), ["core", "net", "ui"]);
+    }
+
+    #[test]
+    fn paths_resolve_against_the_description_directory() {
+        // Tests run from the crate root, which is not the directory of the description.
+        let dir = std::env::temp_dir().join(format!("cpp-project-init-paths-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("project.toml"), r#"src.main_file.path = "main.cpp""#).unwrap();
+        fs::write(dir.join("main.cpp"), "int main() {}").unwrap();
+
+        let project = Project::load(&dir).unwrap();
+        let SourceFile::Path(path) = &project.src.main_file.source else {
+            panic!("main file is not a path");
+        };
+        assert_eq!(project.resolve_path(path), dir.join("main.cpp"));
+        assert_eq!(fs::read_to_string(project.resolve_path(path)).unwrap(), "int main() {}");
+
+        assert_eq!(project.resolve_path("cwd:Cargo.toml"), Path::new("Cargo.toml"));
+        assert!(project.resolve_path("cwd:Cargo.toml").is_file());
+        if cfg!(unix) {
+            assert_eq!(project.resolve_path("/usr/include/stdio.h"), Path::new("/usr/include/stdio.h"));
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn dependency_cycles_are_rejected() {
+        let project = project(indoc! {r#"
+            [libraries]
+            core = {}
+            ui = { public_dependencies = ["core", "net"] }
+            net = { private_dependencies = ["ui"] }
+        "#});
+
+        let Err(Error::DependencyCycle(cycle)) = project.library_order() else {
+            panic!("cycle not detected");
+        };
+        assert_eq!(cycle, ["ui", "net", "ui"]);
+    }
+}
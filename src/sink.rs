@@ -0,0 +1,337 @@
+use std::{
+    fs,
+    io::{self, Seek, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use indexmap::IndexMap;
+
+use crate::tree::PathError;
+
+/// Destination of a rendered [`SourceTree`](crate::tree::SourceTree). Paths passed to a sink are
+/// relative to its root and have already been checked to stay inside it.
+pub trait Sink {
+    fn create_dir(&mut self, path: &Path) -> io::Result<()>;
+
+    /// Writes a file, applying the Unix permission bits in `mode` when given.
+    fn write_file(&mut self, path: &Path, contents: &[u8], mode: Option<u32>) -> io::Result<()>;
+
+    /// Creates a symlink at `path` pointing to `target`, which is relative to the link.
+    fn symlink(&mut self, path: &Path, target: &Path) -> io::Result<()>;
+
+    /// Checks that writing to `path` does not escape the sink root. Called for every entry
+    /// before anything is written.
+    fn check_path(&self, path: &Path) -> Result<(), PathError> {
+        let _ = path;
+        Ok(())
+    }
+}
+
+/// Writes to a directory on the real filesystem.
+pub struct FsSink {
+    root: PathBuf,
+    canonical_root: Option<PathBuf>,
+}
+
+impl FsSink {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let canonical_root = fs::canonicalize(&root).ok();
+
+        Self { root, canonical_root }
+    }
+}
+
+impl Sink for FsSink {
+    fn create_dir(&mut self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(self.root.join(path))
+    }
+
+    fn write_file(&mut self, path: &Path, contents: &[u8], mode: Option<u32>) -> io::Result<()> {
+        let path = self.root.join(path);
+        fs::write(&path, contents)?;
+
+        #[cfg(unix)]
+        if let Some(mode) = mode {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode))?;
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+
+        Ok(())
+    }
+
+    fn symlink(&mut self, path: &Path, target: &Path) -> io::Result<()> {
+        let path = self.root.join(path);
+
+        #[cfg(unix)]
+        return std::os::unix::fs::symlink(target, path);
+        #[cfg(windows)]
+        return std::os::windows::fs::symlink_file(target, path);
+        #[cfg(not(any(unix, windows)))]
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "symlinks are not supported"));
+    }
+
+    /// Rejects paths going through symlinks that already exist in the output directory and
+    /// point outside of it.
+    fn check_path(&self, path: &Path) -> Result<(), PathError> {
+        let Some(canonical_root) = &self.canonical_root else {
+            return Ok(());
+        };
+
+        let mut current = self.root.clone();
+        for component in path.components() {
+            current.push(component);
+            match fs::symlink_metadata(&current) {
+                Ok(m) if m.file_type().is_symlink() => {
+                    let inside = fs::canonicalize(&current).is_ok_and(|p| p.starts_with(canonical_root));
+                    if !inside {
+                        return Err(PathError::SymlinkEscape(path.to_string_lossy().into_owned()));
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MemoryEntry {
+    Directory,
+    File { contents: Vec<u8>, mode: Option<u32> },
+    Symlink(PathBuf),
+}
+
+/// Keeps rendered entries in memory, in the order they were written.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemorySink {
+    pub entries: IndexMap<PathBuf, MemoryEntry>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the contents of the file at `path`, if there is one.
+    pub fn file(&self, path: impl AsRef<Path>) -> Option<&[u8]> {
+        match self.entries.get(path.as_ref())? {
+            MemoryEntry::File { contents, .. } => Some(contents),
+            MemoryEntry::Directory | MemoryEntry::Symlink(_) => None,
+        }
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (&Path, &[u8])> {
+        self.entries.iter().filter_map(|(path, entry)| match entry {
+            MemoryEntry::File { contents, .. } => Some((path.as_path(), contents.as_slice())),
+            MemoryEntry::Directory | MemoryEntry::Symlink(_) => None,
+        })
+    }
+}
+
+impl Sink for MemorySink {
+    fn create_dir(&mut self, path: &Path) -> io::Result<()> {
+        self.entries.insert(path.to_path_buf(), MemoryEntry::Directory);
+        Ok(())
+    }
+
+    fn write_file(&mut self, path: &Path, contents: &[u8], mode: Option<u32>) -> io::Result<()> {
+        let entry = MemoryEntry::File {
+            contents: contents.to_vec(),
+            mode,
+        };
+        self.entries.insert(path.to_path_buf(), entry);
+        Ok(())
+    }
+
+    fn symlink(&mut self, path: &Path, target: &Path) -> io::Result<()> {
+        self.entries.insert(path.to_path_buf(), MemoryEntry::Symlink(target.to_path_buf()));
+        Ok(())
+    }
+}
+
+/// Writes a tar archive. Wrap the writer in an encoder to get a compressed archive.
+pub struct TarSink<W: Write> {
+    builder: tar::Builder<W>,
+    mtime: u64,
+}
+
+impl<W: Write> TarSink<W> {
+    pub fn new(writer: W) -> Self {
+        let mtime = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        Self {
+            builder: tar::Builder::new(writer),
+            mtime,
+        }
+    }
+
+    /// Writes the end of the archive and returns the underlying writer.
+    pub fn finish(self) -> io::Result<W> {
+        self.builder.into_inner()
+    }
+
+    fn header(&self, kind: tar::EntryType, mode: u32, size: u64) -> tar::Header {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(kind);
+        header.set_mode(mode);
+        header.set_size(size);
+        header.set_mtime(self.mtime);
+        header
+    }
+}
+
+impl<W: Write> Sink for TarSink<W> {
+    fn create_dir(&mut self, path: &Path) -> io::Result<()> {
+        let mut header = self.header(tar::EntryType::Directory, 0o755, 0);
+        self.builder.append_data(&mut header, path, io::empty())
+    }
+
+    fn write_file(&mut self, path: &Path, contents: &[u8], mode: Option<u32>) -> io::Result<()> {
+        let mode = mode.unwrap_or(0o644);
+        let mut header = self.header(tar::EntryType::Regular, mode, contents.len() as u64);
+        self.builder.append_data(&mut header, path, contents)
+    }
+
+    fn symlink(&mut self, path: &Path, target: &Path) -> io::Result<()> {
+        let mut header = self.header(tar::EntryType::Symlink, 0o777, 0);
+        self.builder.append_link(&mut header, path, target)
+    }
+}
+
+/// Writes a zip archive.
+pub struct ZipSink<W: Write + Seek> {
+    writer: zip::ZipWriter<W>,
+}
+
+impl<W: Write + Seek> ZipSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: zip::ZipWriter::new(writer),
+        }
+    }
+
+    /// Writes the central directory and returns the underlying writer.
+    pub fn finish(self) -> io::Result<W> {
+        self.writer.finish().map_err(io::Error::other)
+    }
+}
+
+fn zip_name(path: &Path) -> String {
+    path.iter()
+        .map(|c| c.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+impl<W: Write + Seek> Sink for ZipSink<W> {
+    fn create_dir(&mut self, path: &Path) -> io::Result<()> {
+        let options = zip::write::SimpleFileOptions::default().unix_permissions(0o755);
+        self.writer
+            .add_directory(zip_name(path), options)
+            .map_err(io::Error::other)
+    }
+
+    fn write_file(&mut self, path: &Path, contents: &[u8], mode: Option<u32>) -> io::Result<()> {
+        let options = zip::write::SimpleFileOptions::default().unix_permissions(mode.unwrap_or(0o644));
+        self.writer
+            .start_file(zip_name(path), options)
+            .map_err(io::Error::other)?;
+        self.writer.write_all(contents)
+    }
+
+    fn symlink(&mut self, path: &Path, target: &Path) -> io::Result<()> {
+        let options = zip::write::SimpleFileOptions::default();
+        self.writer
+            .add_symlink(zip_name(path), zip_name(target), options)
+            .map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use super::*;
+    use crate::tree::{SourceNode, SourceTree};
+
+    fn tree() -> SourceTree {
+        let mut tree = SourceTree::default();
+        tree.insert(SourceNode::File {
+            name: "src/main.cpp".into(),
+            contents: b"int main() {}".to_vec(),
+            mode: None,
+        });
+        tree.insert(SourceNode::File {
+            name: "run.sh".into(),
+            contents: b"#!/bin/sh".to_vec(),
+            mode: Some(0o755),
+        });
+        tree.insert(SourceNode::Symlink { name: "main.cpp".into(), target: "src/main.cpp".into() });
+        tree
+    }
+
+    #[test]
+    fn tar_round_trip() {
+        let mut sink = TarSink::new(Vec::new());
+        tree().write(&mut sink).unwrap();
+        let archive = sink.finish().unwrap();
+
+        let mut entries = Vec::new();
+        for entry in tar::Archive::new(archive.as_slice()).entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().to_string_lossy().into_owned();
+            let mode = entry.header().mode().unwrap();
+            let kind = entry.header().entry_type();
+            let link = entry.link_name().unwrap().map(|l| l.to_string_lossy().into_owned());
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents).unwrap();
+            entries.push((path, kind, mode, link.unwrap_or(contents)));
+        }
+
+        assert_eq!(
+            entries,
+            [
+                ("src".to_owned(), tar::EntryType::Directory, 0o755, String::new()),
+                ("src/main.cpp".to_owned(), tar::EntryType::Regular, 0o644, "int main() {}".to_owned()),
+                ("run.sh".to_owned(), tar::EntryType::Regular, 0o755, "#!/bin/sh".to_owned()),
+                ("main.cpp".to_owned(), tar::EntryType::Symlink, 0o777, "src/main.cpp".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn zip_round_trip() {
+        let mut sink = ZipSink::new(Cursor::new(Vec::new()));
+        tree().write(&mut sink).unwrap();
+        let mut archive = zip::ZipArchive::new(sink.finish().unwrap()).unwrap();
+
+        let mut entries = Vec::new();
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i).unwrap();
+            let name = entry.name().unwrap().into_owned();
+            let mode = entry.unix_mode().unwrap() & 0o777;
+            let is_symlink = entry.is_symlink();
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents).unwrap();
+            entries.push((name, is_symlink, mode, contents));
+        }
+
+        assert_eq!(
+            entries,
+            [
+                ("src/".to_owned(), false, 0o755, String::new()),
+                ("src/main.cpp".to_owned(), false, 0o644, "int main() {}".to_owned()),
+                ("run.sh".to_owned(), false, 0o755, "#!/bin/sh".to_owned()),
+                ("main.cpp".to_owned(), true, 0o777, "src/main.cpp".to_owned()),
+            ]
+        );
+    }
+}
@@ -2,3 +2,33 @@ pub fn trim_newline(s: &mut String) {
     s.truncate(s.trim_end().len());
     s.push('\n');
 }
+
+/// Whether `name` can be used as a C preprocessor macro name.
+pub fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Whether `version` is a version accepted by CMake's `project()`: up to four dot-separated
+/// numbers.
+pub fn is_version(version: &str) -> bool {
+    version.split('.').count() <= 4
+        && version.split('.').all(|p| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Quotes `s` as a CMake quoted argument, escaping characters CMake would interpret.
+pub fn cmake_quote(s: &str) -> String {
+    format!("\"{}\"", cmake_escape(s))
+}
+
+/// Escapes the characters CMake would interpret inside a quoted argument.
+pub fn cmake_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '\\' | '"' | '$') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
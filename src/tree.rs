@@ -1,22 +1,263 @@
-use std::{fs, path::{Path, PathBuf}};
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Component, Path, PathBuf},
+    process,
+};
 
-#[derive(Default)]
+use crate::{
+    Error, Result,
+    sink::{FsSink, Sink},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PathError {
+    #[error("`{0}` is an absolute path")]
+    Absolute(String),
+
+    #[error("`{0}` points outside of the project directory")]
+    ParentDir(String),
+
+    #[error("`{0}` does not name anything inside the project directory")]
+    Empty(String),
+
+    #[error("`{0}` escapes the project directory through a symlink")]
+    SymlinkEscape(String),
+
+    #[error("`{0}` is generated more than once")]
+    Duplicate(String),
+}
+
+impl PathError {
+    /// The offending entry, relative to the project root.
+    pub fn entry(&self) -> &str {
+        match self {
+            PathError::Absolute(entry)
+            | PathError::ParentDir(entry)
+            | PathError::Empty(entry)
+            | PathError::SymlinkEscape(entry)
+            | PathError::Duplicate(entry) => entry,
+        }
+    }
+}
+
+/// Converts a relative entry name to the `/`-separated form used in generated build files,
+/// rejecting names that would be written outside of the directory they belong to, or in place
+/// of the directory itself.
+pub fn normalize_name(name: &str) -> Result<String, PathError> {
+    let is_drive = name.len() >= 2 && name.as_bytes()[1] == b':' && name.as_bytes()[0].is_ascii_alphabetic();
+    if name.starts_with(['/', '\\']) || is_drive {
+        return Err(PathError::Absolute(name.to_owned()));
+    }
+
+    let components: Vec<&str> = name
+        .split(['/', '\\'])
+        .filter(|c| !c.is_empty() && *c != ".")
+        .collect();
+
+    if components.contains(&"..") {
+        return Err(PathError::ParentDir(name.to_owned()));
+    }
+    if components.is_empty() {
+        return Err(PathError::Empty(name.to_owned()));
+    }
+
+    Ok(components.join("/"))
+}
+
+/// Whether a symlink in `dir` pointing to `target` stays inside the root `dir` is relative to.
+/// Parent components are only accepted at the start of the target: after a component that may
+/// itself be a symlink, `..` would not be resolved lexically.
+fn target_stays_inside(dir: &Path, target: &Path) -> bool {
+    let mut depth = dir.components().count();
+    let mut descended = false;
+    for component in target.components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(_) => descended = true,
+            Component::ParentDir if !descended && depth > 0 => depth -= 1,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+
+    true
+}
+
+/// Returns a hidden path next to `path` for temporary use while rendering.
+fn sibling(path: &Path, tag: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{name}.{tag}-{}", process::id()))
+}
+
+/// Change made to the output directory while merging, undone in reverse order on failure.
+enum MergeStep {
+    /// A directory was created.
+    Created(PathBuf),
+    /// An existing entry was moved from the first path to the backup at the second one.
+    BackedUp(PathBuf, PathBuf),
+    /// A generated file or symlink was moved into place.
+    Moved(PathBuf),
+}
+
+/// Moves the rendered entries of `from` into the existing directory `to`, moving the entries
+/// they replace into `backup`.
+fn merge_dir(from: &Path, to: &Path, backup: &Path, steps: &mut Vec<MergeStep>) -> io::Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let source = entry.path();
+        let dest = to.join(entry.file_name());
+        let saved = backup.join(entry.file_name());
+        let exists = fs::symlink_metadata(&dest).is_ok();
+
+        if entry.file_type()?.is_dir() {
+            if !fs::metadata(&dest).is_ok_and(|m| m.is_dir()) {
+                if exists {
+                    fs::create_dir_all(backup)?;
+                    fs::rename(&dest, &saved)?;
+                    steps.push(MergeStep::BackedUp(dest.clone(), saved.clone()));
+                }
+                fs::create_dir(&dest)?;
+                steps.push(MergeStep::Created(dest.clone()));
+            }
+            merge_dir(&source, &dest, &saved, steps)?;
+            continue;
+        }
+
+        if exists {
+            fs::create_dir_all(backup)?;
+            fs::rename(&dest, &saved)?;
+            steps.push(MergeStep::BackedUp(dest.clone(), saved));
+        }
+        fs::rename(&source, &dest)?;
+        steps.push(MergeStep::Moved(dest));
+    }
+
+    Ok(())
+}
+
+/// Restores the output directory to its state before an interrupted merge.
+fn rollback(steps: Vec<MergeStep>) {
+    for step in steps.into_iter().rev() {
+        let _ = match step {
+            MergeStep::Created(dir) => fs::remove_dir(dir),
+            MergeStep::BackedUp(dest, saved) => fs::rename(saved, dest),
+            MergeStep::Moved(dest) => fs::remove_file(dest),
+        };
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
 pub struct SourceTree {
     pub children: Vec<SourceNode>,
 }
 
 impl SourceTree {
-    pub fn render(&self, path: &Path) -> std::io::Result<()> {
-        let mut stack = vec![path];
+    pub fn insert(&mut self, node: SourceNode) {
+        SourceNode::insert(&mut self.children, node);
+    }
+
+    /// Whether a file or symlink is generated at the `/`-separated `path`.
+    pub fn contains_file(&self, path: &str) -> bool {
+        let mut nodes = &self.children;
+        let mut parts = path.split('/').peekable();
+        while let Some(part) = parts.next() {
+            let Some(node) = nodes.iter().find(|n| n.name() == Path::new(part)) else {
+                return false;
+            };
+            match node {
+                SourceNode::Directory { children, .. } => nodes = children,
+                _ => return parts.peek().is_none(),
+            }
+        }
+
+        false
+    }
+
+    /// Renders the tree into a directory on the filesystem.
+    ///
+    /// The tree is written into a temporary sibling directory first, so a failure never leaves
+    /// a partially generated project. A new or empty output directory, or any existing one when
+    /// `replace` is set, is then replaced by it. Otherwise the generated files are merged into
+    /// the existing directory, overwriting files with the same name and keeping the rest, and
+    /// the merge is rolled back if it fails halfway.
+    pub fn render(&self, path: &Path, replace: bool) -> Result<()> {
+        let write_error = |path: &Path| {
+            let path = path.to_path_buf();
+            move |source| Error::Write { path, source }
+        };
+
+        let path = match path.file_name() {
+            Some(_) => path.to_path_buf(),
+            None => fs::canonicalize(path).map_err(write_error(path))?,
+        };
+
+        let existing = match fs::read_dir(&path) {
+            Ok(mut entries) => Some(entries.next().is_some()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(write_error(&path)(e)),
+        };
+        let merge = existing == Some(true) && !replace;
+        if merge {
+            // Existing symlinks in the output directory are followed when merging.
+            SourceNode::check_all(&self.children, &FsSink::new(&path), Path::new(""))?;
+        }
+
+        let temp = sibling(&path, "tmp");
+        let result = fs::create_dir_all(&temp)
+            .map_err(write_error(&temp))
+            .and_then(|()| self.write(&mut FsSink::new(&temp)));
+        if let Err(e) = result {
+            let _ = fs::remove_dir_all(&temp);
+            return Err(e);
+        }
+
+        let backup = sibling(&path, "old");
+        if merge {
+            let mut steps = Vec::new();
+            let result = merge_dir(&temp, &path, &backup, &mut steps);
+            if result.is_err() {
+                rollback(steps);
+            }
+            let _ = fs::remove_dir_all(&temp);
+            let _ = fs::remove_dir_all(&backup);
+            return result.map_err(write_error(&path));
+        }
+
+        if existing.is_some()
+            && let Err(e) = fs::rename(&path, &backup)
+        {
+            let _ = fs::remove_dir_all(&temp);
+            return Err(write_error(&path)(e));
+        }
+
+        if let Err(e) = fs::rename(&temp, &path) {
+            if existing.is_some() {
+                let _ = fs::rename(&backup, &path);
+            }
+            let _ = fs::remove_dir_all(&temp);
+            return Err(write_error(&path)(e));
+        }
+
+        if existing.is_some() {
+            let _ = fs::remove_dir_all(&backup);
+        }
+
+        Ok(())
+    }
+
+    /// Renders the tree into `sink`, checking every entry before anything is written.
+    pub fn write(&self, sink: &mut dyn Sink) -> Result<()> {
+        SourceNode::check_all(&self.children, sink, Path::new(""))?;
 
         for child in &self.children {
-            child.render(&mut stack)?;
+            child.write(sink, Path::new(""))?;
         }
 
         Ok(())
     }
 }
 
+#[derive(Debug, PartialEq, Eq)]
 pub enum SourceNode {
     Directory {
         name: PathBuf,
@@ -25,31 +266,327 @@ pub enum SourceNode {
     File {
         name: PathBuf,
         contents: Vec<u8>,
-    }
+        /// Unix permission bits. Sinks use their default file mode when unset.
+        mode: Option<u32>,
+    },
+    Symlink {
+        name: PathBuf,
+        target: PathBuf,
+    },
 }
 
 impl SourceNode {
-    fn render<'a>(&'a self, stack: &mut Vec<&'a Path>) -> std::io::Result<()> {
+    pub fn name(&self) -> &Path {
+        match self {
+            SourceNode::Directory { name, .. }
+            | SourceNode::File { name, .. }
+            | SourceNode::Symlink { name, .. } => name,
+        }
+    }
+
+    fn set_name(&mut self, new_name: PathBuf) {
         match self {
+            SourceNode::Directory { name, .. }
+            | SourceNode::File { name, .. }
+            | SourceNode::Symlink { name, .. } => *name = new_name,
+        }
+    }
+
+    /// Inserts a node whose name may be a nested relative path, creating intermediate
+    /// directories as needed and merging directories that already exist.
+    pub fn insert(nodes: &mut Vec<SourceNode>, mut node: SourceNode) {
+        let path = node.name().to_path_buf();
+        let Some(file_name) = path.file_name() else {
+            nodes.push(node);
+            return;
+        };
+        node.set_name(file_name.into());
+
+        let mut nodes = nodes;
+        for dir in path.parent().into_iter().flatten() {
+            nodes = Self::directory_children(nodes, Path::new(dir));
+        }
+
+        match node {
             SourceNode::Directory { name, children } => {
-                stack.push(name);
-                let path = PathBuf::from_iter(stack.iter());
-                fs::create_dir_all(&path)?;
+                let existing = Self::directory_children(nodes, &name);
+                for child in children {
+                    Self::insert(existing, child);
+                }
+            }
+            node => nodes.push(node),
+        }
+    }
+
+    /// Inserts a file at a relative path, creating intermediate directories as needed.
+    pub fn insert_file(nodes: &mut Vec<SourceNode>, path: &Path, contents: Vec<u8>, mode: Option<u32>) {
+        Self::insert(nodes, SourceNode::File { name: path.into(), contents, mode });
+    }
+
+    /// Returns the children of the directory named `dir`, creating it if it does not exist.
+    fn directory_children<'a>(nodes: &'a mut Vec<SourceNode>, dir: &Path) -> &'a mut Vec<SourceNode> {
+        let index = nodes
+            .iter()
+            .position(|n| matches!(n, SourceNode::Directory { name, .. } if name == dir))
+            .unwrap_or_else(|| {
+                nodes.push(SourceNode::Directory { name: dir.into(), children: Vec::new() });
+                nodes.len() - 1
+            });
+
+        let SourceNode::Directory { children, .. } = &mut nodes[index] else {
+            unreachable!()
+        };
+        children
+    }
+
+    /// Checks that the node and its children are written inside the sink root.
+    fn check(&self, sink: &dyn Sink, parent: &Path) -> Result<(), PathError> {
+        let relative = parent.join(self.name());
+        let entry = || relative.to_string_lossy().into_owned();
+
+        for component in self.name().components() {
+            match component {
+                Component::Normal(_) | Component::CurDir => {}
+                Component::ParentDir => return Err(PathError::ParentDir(entry())),
+                Component::RootDir | Component::Prefix(_) => return Err(PathError::Absolute(entry())),
+            }
+        }
+
+        sink.check_path(&relative)?;
+
+        if let SourceNode::Symlink { target, .. } = self
+            && !target_stays_inside(relative.parent().unwrap_or(Path::new("")), target)
+        {
+            return Err(PathError::SymlinkEscape(entry()));
+        }
+
+        if let SourceNode::Directory { children, .. } = self {
+            Self::check_all(children, sink, &relative)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks sibling nodes. A second entry with the same name would be written through the
+    /// first one, which for a symlink could point anywhere, so duplicates are rejected.
+    fn check_all(nodes: &[SourceNode], sink: &dyn Sink, parent: &Path) -> Result<(), PathError> {
+        let mut names = HashSet::new();
+        for node in nodes {
+            if !names.insert(node.name()) {
+                let path = parent.join(node.name());
+                return Err(PathError::Duplicate(path.to_string_lossy().into_owned()));
+            }
+            node.check(sink, parent)?;
+        }
+
+        Ok(())
+    }
+
+    fn write(&self, sink: &mut dyn Sink, parent: &Path) -> Result<()> {
+        let path = parent.join(self.name());
+        let write_error = |source| Error::Write {
+            path: path.clone(),
+            source,
+        };
+
+        match self {
+            SourceNode::Directory { children, .. } => {
+                sink.create_dir(&path).map_err(write_error)?;
 
                 for child in children {
-                    child.render(stack)?;
+                    child.write(sink, &path)?;
                 }
-                stack.pop();
-            },
+            }
+
+            SourceNode::File { contents, mode, .. } => {
+                sink.write_file(&path, contents, *mode).map_err(write_error)?;
+            }
 
-            SourceNode::File { name, contents } => {
-                stack.push(name);
-                let path = PathBuf::from_iter(stack.iter());
-                fs::write(path, contents)?;
-                stack.pop();
-            },
+            SourceNode::Symlink { target, .. } => {
+                sink.symlink(&path, target).map_err(write_error)?;
+            }
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::MemorySink;
+
+    fn file(name: &str, contents: &str) -> SourceNode {
+        SourceNode::File {
+            name: name.into(),
+            contents: contents.into(),
+            mode: None,
+        }
+    }
+
+    #[test]
+    fn names_outside_of_the_project_are_rejected() {
+        assert_eq!(normalize_name("./src//main.cpp").unwrap(), "src/main.cpp");
+
+        for name in ["/etc/passwd", "\\server\\share", "C:\\Windows"] {
+            let error = normalize_name(name).unwrap_err();
+            assert!(matches!(&error, PathError::Absolute(entry) if entry == name), "{error}");
+            assert_eq!(error.to_string(), format!("`{name}` is an absolute path"));
+        }
+
+        for name in ["../outside.cpp", "src/../../outside.cpp", "src\\..\\..\\outside.cpp"] {
+            let error = normalize_name(name).unwrap_err();
+            assert!(matches!(&error, PathError::ParentDir(entry) if entry == name), "{error}");
+            assert_eq!(error.to_string(), format!("`{name}` points outside of the project directory"));
+        }
+    }
+
+    #[test]
+    fn empty_names_are_rejected() {
+        for name in ["", ".", "./", ".\\.", "./."] {
+            let error = normalize_name(name).unwrap_err();
+            assert!(matches!(&error, PathError::Empty(entry) if entry == name), "{error}");
+        }
+    }
+
+    #[test]
+    fn unsafe_nodes_are_rejected_before_writing() {
+        let cases = [
+            ("/etc/passwd", "/etc/passwd"),
+            ("../outside.txt", "src/../outside.txt"),
+            ("nested/../../outside.txt", "src/nested/../../outside.txt"),
+        ];
+        for (name, entry) in cases {
+            let mut tree = SourceTree::default();
+            tree.insert(file("src/ok.txt", ""));
+            let SourceNode::Directory { children, .. } = &mut tree.children[0] else {
+                unreachable!()
+            };
+            children.push(file(name, ""));
+
+            let mut sink = MemorySink::new();
+            match tree.write(&mut sink) {
+                Err(Error::UnsafePath(PathError::Absolute(e) | PathError::ParentDir(e))) => {
+                    assert_eq!(e, entry);
+                }
+                other => panic!("{name} was not rejected: {other:?}"),
+            }
+            assert!(sink.entries.is_empty());
+        }
+    }
+
+    #[test]
+    fn failed_render_keeps_existing_output() {
+        let root = std::env::temp_dir().join(format!("cpp-project-init-render-{}", process::id()));
+        let out = root.join("out");
+        let _ = fs::remove_dir_all(&root);
+
+        let mut tree = SourceTree::default();
+        tree.insert(file("old.txt", "old"));
+        tree.render(&out, false).unwrap();
+
+        // File names cannot contain NUL bytes, so the second write fails.
+        let mut broken = SourceTree::default();
+        broken.insert(file("new.txt", "new"));
+        broken.insert(file("bad\0name", ""));
+        assert!(matches!(broken.render(&out, true), Err(Error::Write { .. })));
+
+        assert_eq!(fs::read_to_string(out.join("old.txt")).unwrap(), "old");
+        assert!(!out.join("new.txt").exists());
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 1);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn render_merges_into_existing_directory() {
+        let root = std::env::temp_dir().join(format!("cpp-project-init-merge-{}", process::id()));
+        let out = root.join("out");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(out.join("src")).unwrap();
+        fs::write(out.join("README.md"), "keep").unwrap();
+        fs::write(out.join("src/main.cpp"), "old").unwrap();
+        fs::write(out.join("src/notes.txt"), "keep").unwrap();
+
+        let mut tree = SourceTree::default();
+        tree.insert(file("src/main.cpp", "new"));
+        tree.insert(file("src/lib/lib.cpp", "new"));
+        tree.render(&out, false).unwrap();
+
+        assert_eq!(fs::read_to_string(out.join("README.md")).unwrap(), "keep");
+        assert_eq!(fs::read_to_string(out.join("src/notes.txt")).unwrap(), "keep");
+        assert_eq!(fs::read_to_string(out.join("src/main.cpp")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(out.join("src/lib/lib.cpp")).unwrap(), "new");
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 1);
+
+        tree.render(&out, true).unwrap();
+        assert!(!out.join("README.md").exists());
+        assert_eq!(fs::read_to_string(out.join("src/main.cpp")).unwrap(), "new");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn files_cannot_be_written_through_symlinks() {
+        let mut tree = SourceTree::default();
+        tree.insert(SourceNode::Symlink { name: "alias".into(), target: "src".into() });
+        tree.insert(file("alias/main.cpp", ""));
+
+        let mut sink = MemorySink::new();
+        assert!(matches!(tree.write(&mut sink), Err(Error::UnsafePath(PathError::Duplicate(_)))));
+        assert!(sink.entries.is_empty());
+    }
+
+    #[test]
+    fn symlinks_cannot_point_outside_of_the_project() {
+        let escaping = [
+            ("esc", "/etc"),
+            ("esc", "../../../etc"),
+            ("esc", ".."),
+            ("scripts/esc", "../.."),
+            ("scripts/esc", "../src/../.."),
+        ];
+        for (name, target) in escaping {
+            let mut tree = SourceTree::default();
+            tree.insert(file("src/main.cpp", ""));
+            tree.insert(SourceNode::Symlink { name: name.into(), target: target.into() });
+
+            let mut sink = MemorySink::new();
+            match tree.write(&mut sink) {
+                Err(Error::UnsafePath(PathError::SymlinkEscape(entry))) => assert_eq!(entry, name),
+                other => panic!("{name} -> {target} was not rejected: {other:?}"),
+            }
+            assert!(sink.entries.is_empty());
+        }
+
+        let mut tree = SourceTree::default();
+        tree.insert(SourceNode::Symlink { name: "main.cpp".into(), target: "./src/main.cpp".into() });
+        tree.insert(SourceNode::Symlink { name: "scripts/src".into(), target: "../src".into() });
+        tree.write(&mut MemorySink::new()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn render_applies_modes_and_symlinks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = std::env::temp_dir().join(format!("cpp-project-init-modes-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let mut tree = SourceTree::default();
+        tree.insert(SourceNode::File {
+            name: "scripts/build.sh".into(),
+            contents: b"#!/bin/sh".to_vec(),
+            mode: Some(0o755),
+        });
+        tree.insert(SourceNode::Symlink { name: "build.sh".into(), target: "scripts/build.sh".into() });
+        tree.render(&root, false).unwrap();
+
+        let mode = fs::metadata(root.join("scripts/build.sh")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert_eq!(fs::read_link(root.join("build.sh")).unwrap(), Path::new("scripts/build.sh"));
+        assert_eq!(fs::read_to_string(root.join("build.sh")).unwrap(), "#!/bin/sh");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
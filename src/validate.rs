@@ -0,0 +1,707 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    Error, Result,
+    project::{
+        Format, Layout, LibraryKind, PackageGenerator, ParseError, Project, ProjectKind, SourceFile, decode_base64,
+        is_compiled,
+    },
+    generators::{CmakeProject, Generator},
+    sink::MemorySink,
+    strings::{is_identifier, is_version},
+    tree::normalize_name,
+};
+
+pub struct Report {
+    pub file: PathBuf,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Error)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub file: PathBuf,
+    pub location: Option<Location>,
+}
+
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+    pub source_line: String,
+}
+
+impl Location {
+    fn from_offset(contents: &str, offset: usize) -> Self {
+        let offset = offset.min(contents.len());
+        let line_start = contents[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line = contents[..line_start].matches('\n').count() + 1;
+        let column = contents[line_start..offset].chars().count() + 1;
+
+        Self::from_line_column(contents, line, column)
+    }
+
+    fn from_line_column(contents: &str, line: usize, column: usize) -> Self {
+        let source_line = contents
+            .lines()
+            .nth(line.saturating_sub(1))
+            .unwrap_or_default()
+            .to_owned();
+
+        Self { line, column, source_line }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let file = self.file.display();
+
+        let Some(location) = &self.location else {
+            return write!(f, "{file}: {severity}: {}", self.message);
+        };
+
+        let Location { line, column, source_line } = location;
+        let gutter = " ".repeat(line.to_string().len());
+        let padding: String = source_line
+            .chars()
+            .take(column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        writeln!(f, "{file}:{line}:{column}: {severity}: {}", self.message)?;
+        writeln!(f, "{gutter} |")?;
+        writeln!(f, "{line} | {source_line}")?;
+        write!(f, "{gutter} | {padding}^")
+    }
+}
+
+pub fn validate(input: impl AsRef<Path>) -> Result<Report> {
+    let file = Project::find_description(input.as_ref())?;
+    let format = Format::from_path(&file).ok_or_else(|| Error::UnsupportedFormat(file.clone()))?;
+    let contents = fs::read_to_string(&file).map_err(|source| Error::ReadDescription {
+        path: file.clone(),
+        source,
+    })?;
+
+    let diagnostics = check_description(&file, &contents, format);
+
+    Ok(Report { file, diagnostics })
+}
+
+fn check_description(file: &Path, contents: &str, format: Format) -> Vec<Diagnostic> {
+    let project = match Project::parse(file, contents, format) {
+        Ok(project) => project,
+        Err(e) => return vec![parse_diagnostic(file, contents, e)],
+    };
+
+    let mut checker = Checker::new(file, contents);
+    checker.check(&project);
+    if !checker.diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        checker.check_generation(project);
+    }
+
+    checker.diagnostics
+}
+
+fn parse_diagnostic(file: &Path, contents: &str, error: ParseError) -> Diagnostic {
+    let (message, location) = match error {
+        ParseError::Toml(e) => (
+            e.message().to_owned(),
+            e.span().map(|s| Location::from_offset(contents, s.start)),
+        ),
+        ParseError::Yaml(e) => (
+            strip_location(e.to_string()),
+            e.location()
+                .map(|l| Location::from_line_column(contents, l.line(), l.column())),
+        ),
+        ParseError::Json(e) => (
+            strip_location(e.to_string()),
+            (e.line() != 0).then(|| Location::from_line_column(contents, e.line(), e.column())),
+        ),
+        ParseError::Xml(e) => (e.to_string(), None),
+    };
+
+    Diagnostic {
+        severity: Severity::Error,
+        message,
+        file: file.to_path_buf(),
+        location,
+    }
+}
+
+fn strip_location(mut message: String) -> String {
+    if let Some(i) = message.rfind(" at line ") {
+        message.truncate(i);
+    }
+    message
+}
+
+struct Checker<'a> {
+    file: &'a Path,
+    contents: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+    fn new(file: &'a Path, contents: &'a str) -> Self {
+        Self {
+            file,
+            contents,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn check(&mut self, project: &Project) {
+        let src = &project.src;
+
+        let mut entries = vec![&src.dir, &src.main_file.name];
+        entries.extend(src.sources.keys());
+        entries.extend(src.headers.keys());
+        entries.extend(src.private_headers.keys());
+        for executable in src.executables.values() {
+            entries.push(&executable.main_file.name);
+            entries.extend(executable.sources.keys());
+        }
+        if let Some(testing) = &project.testing {
+            entries.push(&testing.dir);
+            entries.extend(testing.sources.keys());
+        }
+        for (name, library) in &project.libraries {
+            entries.push(library.dir.as_ref().unwrap_or(name));
+            entries.extend(library.sources.keys());
+            entries.extend(library.headers.keys());
+        }
+        entries.extend(src.assets.keys());
+        entries.extend(project.files.keys());
+        entries.extend(project.packaging.iter().filter_map(|p| p.license_file.as_ref()));
+        for entry in entries {
+            if let Err(e) = normalize_name(entry) {
+                self.report(Severity::Error, e.to_string(), entry, 0);
+            }
+        }
+
+        let public_headers = src.headers.keys().map(String::as_str);
+        let (flat_headers, include_headers) = match src.layout {
+            Layout::Flat => (Some(public_headers), None),
+            Layout::Include => (None, Some(public_headers)),
+        };
+        if let Some(headers) = include_headers {
+            self.check_duplicates(&format!("include/{}", project.name), false, headers);
+        }
+
+        let src_names = std::iter::once(src.main_file.name.as_str())
+            .chain(src.sources.keys().map(String::as_str))
+            .chain(flat_headers.into_iter().flatten())
+            .chain(src.private_headers.keys().map(String::as_str))
+            .chain(src.executables.values().flat_map(|e| {
+                std::iter::once(e.main_file.name.as_str()).chain(e.sources.keys().map(String::as_str))
+            }));
+        self.check_duplicates(&src.dir, true, src_names);
+
+        for (name, library) in &project.libraries {
+            let names = library.sources.keys().chain(library.headers.keys()).map(String::as_str);
+            self.check_duplicates(library.dir.as_ref().unwrap_or(name), true, names);
+        }
+
+        let mut targets = HashSet::from([project.name.clone(), format!("{}_lib", project.name)]);
+        if !src.assets.is_empty() {
+            targets.insert(format!("{}_assets", project.name));
+        }
+        let declared = src
+            .executables
+            .keys()
+            .map(|name| ("executable", name))
+            .chain(project.libraries.keys().map(|name| ("library", name)));
+        for (kind, name) in declared {
+            if !targets.insert(name.clone()) {
+                self.report(
+                    Severity::Error,
+                    format!("{kind} `{name}` has the same name as another target"),
+                    name,
+                    0,
+                );
+            }
+        }
+        if project.install.is_some() && project.libraries.contains_key("lib") {
+            self.report_at(
+                Severity::Error,
+                "library `lib` is reserved for the project library in the install export".to_owned(),
+                find_key(self.contents, "lib"),
+            );
+        }
+
+        let interface_sources = project
+            .libraries
+            .iter()
+            .filter(|(_, l)| l.kind == Some(LibraryKind::Interface))
+            .flat_map(|(name, l)| l.sources.keys().map(move |s| (name, s)))
+            .chain(
+                src.sources
+                    .keys()
+                    .filter(|_| src.library_kind == Some(LibraryKind::Interface))
+                    .map(|s| (&project.name, s)),
+            );
+        for (library, source) in interface_sources {
+            if is_compiled(source) {
+                self.report(
+                    Severity::Error,
+                    format!("header-only library `{library}` cannot have compiled source `{source}`"),
+                    source,
+                    0,
+                );
+            }
+        }
+
+        if project.kind == ProjectKind::Library && !project.has_project_library() {
+            self.report(
+                Severity::Warning,
+                "library project has no compiled sources, so no library is generated; \
+                 set `library_kind` to `interface` for a header-only library"
+                    .to_owned(),
+                "library",
+                0,
+            );
+        }
+
+        if let Some(version) = &project.version
+            && !is_version(version)
+        {
+            self.report(
+                Severity::Error,
+                format!("version `{version}` must be up to four dot-separated numbers, e.g. `1.2.0`"),
+                version,
+                0,
+            );
+        }
+
+        for (library, package) in project.exported_packages() {
+            // Point at the dependency in the list of the library when it can be found.
+            let offset = find_key(self.contents, &library)
+                .and_then(|key| {
+                    let start = key + library.len();
+                    find_token(&self.contents[start..], &package, 0).map(|offset| start + offset)
+                })
+                .or_else(|| find_token(self.contents, &package, 0));
+            self.report_at(
+                Severity::Error,
+                format!(
+                    "installed library `{library}` cannot link package `{package}`: packages are not \
+                     installed, so only shared libraries may link them, and only privately"
+                ),
+                offset,
+            );
+        }
+
+        if let Some(config_header) = &project.config_header {
+            if project.version.as_ref().is_some_and(|v| v.split('.').count() < 3) {
+                self.report(
+                    Severity::Warning,
+                    "version has no patch number, so the config header defines it as empty".to_owned(),
+                    "version",
+                    0,
+                );
+            }
+            for name in config_header.options.keys() {
+                if !is_identifier(name) {
+                    self.report(
+                        Severity::Error,
+                        format!("option `{name}` is not a valid macro name"),
+                        name,
+                        0,
+                    );
+                }
+            }
+        }
+
+        if let Some(packaging) = &project.packaging {
+            if packaging.generators.is_empty() {
+                self.report(Severity::Error, "packaging needs at least one generator".to_owned(), "generators", 0);
+            }
+            if packaging.generators.contains(&PackageGenerator::Deb)
+                && packaging.maintainer.is_none()
+                && project.authors.is_empty()
+            {
+                self.report(
+                    Severity::Error,
+                    "Debian packages need a `maintainer` or project `authors`".to_owned(),
+                    "packaging",
+                    0,
+                );
+            }
+        }
+
+        if let Err(Error::DependencyCycle(cycle)) = project.library_order() {
+            // Point at the dependency closing the cycle, in the list of the last library.
+            let [.., last, first] = cycle.as_slice() else {
+                unreachable!("a cycle has at least two entries")
+            };
+            let offset = find_key(self.contents, last).and_then(|key| {
+                let start = key + last.len();
+                find_token(&self.contents[start..], first, 0).map(|offset| start + offset)
+            });
+            self.report_at(
+                Severity::Error,
+                format!("libraries depend on each other in a cycle: {}", cycle.join(" -> ")),
+                offset,
+            );
+        }
+
+        if let Some(testing) = &project.testing {
+            if testing.dir == src.dir {
+                self.report(
+                    Severity::Error,
+                    format!("tests directory `{}` is the same as the sources directory", testing.dir),
+                    &testing.dir,
+                    1,
+                );
+            } else {
+                let test_names = testing.sources.keys().map(String::as_str);
+                self.check_duplicates(&testing.dir, true, test_names);
+            }
+        }
+
+        self.check_duplicates("assets", false, src.assets.keys().map(String::as_str));
+        self.check_duplicates(".", true, project.files.keys().map(String::as_str));
+        for (name, file) in &project.files {
+            if file.mode.is_some() && matches!(file.source, SourceFile::Symlink(_)) {
+                self.report(Severity::Error, format!("symlink `{name}` cannot have a mode"), name, 0);
+            }
+        }
+
+        let mut all_sources: Vec<&SourceFile> = vec![&src.main_file.source];
+        all_sources.extend(src.sources.values());
+        all_sources.extend(src.headers.values());
+        all_sources.extend(src.private_headers.values());
+        for executable in src.executables.values() {
+            all_sources.push(&executable.main_file.source);
+            all_sources.extend(executable.sources.values());
+        }
+        for library in project.libraries.values() {
+            all_sources.extend(library.sources.values());
+            all_sources.extend(library.headers.values());
+        }
+        all_sources.extend(src.assets.values());
+        if let Some(testing) = &project.testing {
+            all_sources.extend(testing.sources.values());
+        }
+        all_sources.extend(project.files.values().map(|f| &f.source));
+        for source in all_sources {
+            match source {
+                SourceFile::Path(path) if !project.resolve_path(path).is_file() => {
+                    self.report(
+                        Severity::Error,
+                        format!("source file `{path}` does not exist"),
+                        path,
+                        0,
+                    );
+                }
+                SourceFile::Dir { path } if !project.resolve_path(path).is_dir() => {
+                    self.report(
+                        Severity::Error,
+                        format!("source directory `{path}` does not exist"),
+                        path,
+                        0,
+                    );
+                }
+                SourceFile::Base64(text) => {
+                    if let Err(e) = decode_base64(text) {
+                        self.report(Severity::Error, format!("invalid base64 contents: {e}"), text.trim(), 0);
+                    }
+                }
+                SourceFile::Glob { pattern } => {
+                    let resolved = project.resolve_path(pattern);
+                    match glob::glob(&resolved.to_string_lossy()).map(|mut p| p.next()) {
+                        Ok(None) => self.report(
+                            Severity::Warning,
+                            format!("pattern `{pattern}` does not match any files"),
+                            pattern,
+                            0,
+                        ),
+                        Ok(Some(_)) => {}
+                        Err(e) => self.report(
+                            Severity::Error,
+                            format!("invalid pattern `{pattern}`: {e}"),
+                            pattern,
+                            0,
+                        ),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let executable_libraries = src.executables.values().flat_map(|e| &e.libraries);
+        let dependencies = project
+            .libraries
+            .values()
+            .flat_map(|l| l.public_dependencies.iter().chain(&l.private_dependencies));
+        for library in src.libraries.iter().chain(executable_libraries).chain(dependencies) {
+            if !project.packages.contains_key(library) && !project.libraries.contains_key(library) {
+                self.report(
+                    Severity::Warning,
+                    format!("library `{library}` is not provided by any package or internal library"),
+                    library,
+                    0,
+                );
+            }
+        }
+    }
+
+    /// Generates the project in memory, reporting the problems only found while laying out
+    /// the generated tree, such as entries written more than once.
+    fn check_generation(&mut self, project: Project) {
+        let result = CmakeProject::try_from(project)
+            .and_then(|generator| generator.into_tree())
+            .and_then(|tree| tree.write(&mut MemorySink::new()));
+        let Err(error) = result else {
+            return;
+        };
+
+        let entry = match &error {
+            Error::UnsafePath(e) => e.entry(),
+            Error::SourceFile { entry, .. }
+            | Error::InvalidPattern { entry, .. }
+            | Error::InvalidBase64 { entry, .. }
+            | Error::SymlinkMode { entry }
+            | Error::NotASingleFile { entry } => entry,
+            Error::MissingLicenseFile(entry) => entry,
+            _ => "",
+        };
+        // Generated paths are relative to the project root, while the description names
+        // entries relative to their directory.
+        let offset = find_token(self.contents, entry, 0).or_else(|| {
+            let name = entry.rsplit('/').next().unwrap_or_default();
+            find_token(self.contents, name, 0)
+        });
+        self.report_at(Severity::Error, error.to_string(), offset);
+    }
+
+    /// Reports names declared twice in `dir`, or clashing with a generated `CMakeLists.txt`
+    /// when `has_cmakelists` is set.
+    fn check_duplicates<'b>(&mut self, dir: &str, has_cmakelists: bool, names: impl Iterator<Item = &'b str>) {
+        let mut seen = HashSet::new();
+        if has_cmakelists {
+            seen.insert("CMakeLists.txt");
+        }
+        for name in names {
+            if !seen.insert(name) {
+                let occurrence = usize::from(!has_cmakelists || name != "CMakeLists.txt");
+                self.report(
+                    Severity::Error,
+                    format!("file `{name}` is declared more than once in `{dir}`"),
+                    name,
+                    occurrence,
+                );
+            }
+        }
+    }
+
+    /// Reports a problem, pointing at the `occurrence`-th appearance of `token` in the
+    /// description, or at the last one if there are fewer.
+    fn report(&mut self, severity: Severity, message: String, token: &str, occurrence: usize) {
+        let offset = find_token(self.contents, token, occurrence);
+        self.report_at(severity, message, offset);
+    }
+
+    /// Reports a problem at a byte offset into the description, if known.
+    fn report_at(&mut self, severity: Severity, message: String, offset: Option<usize>) {
+        let location = offset.map(|offset| Location::from_offset(self.contents, offset));
+
+        self.diagnostics.push(Diagnostic {
+            severity,
+            message,
+            file: self.file.to_path_buf(),
+            location,
+        });
+    }
+}
+
+fn is_part_of_token(c: char) -> bool {
+    c.is_alphanumeric() || "_-./\\".contains(c)
+}
+
+fn find_token(contents: &str, token: &str, occurrence: usize) -> Option<usize> {
+    let matches: Vec<usize> = token_matches(contents, token).collect();
+
+    matches.get(occurrence).or(matches.last()).copied()
+}
+
+/// Offsets of `token` in `contents` where it is not part of a longer name.
+fn token_matches<'a>(contents: &'a str, token: &'a str) -> impl Iterator<Item = usize> + 'a {
+    contents
+        .match_indices(token)
+        .map(|(i, _)| i)
+        .filter(move |&i| {
+            let before = contents[..i].chars().next_back();
+            let after = contents[i + token.len()..].chars().next();
+            !token.is_empty() && !before.is_some_and(is_part_of_token) && !after.is_some_and(is_part_of_token)
+        })
+}
+
+/// Finds where the internal library `name` is declared: as a `libraries.name` TOML key, or
+/// as a key followed by `:` or `=` in any format.
+fn find_key(contents: &str, name: &str) -> Option<usize> {
+    let dotted = format!("libraries.{name}");
+    if let Some(offset) = find_token(contents, &dotted, 0) {
+        return Some(offset + dotted.len() - name.len());
+    }
+
+    token_matches(contents, name).find(|&i| {
+        let after = contents[i + name.len()..].trim_start_matches(['"', '\'']).trim_start();
+        after.starts_with([':', '='])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    fn check(contents: &str) -> Vec<Diagnostic> {
+        check_description(Path::new("project.toml"), contents, Format::Toml)
+    }
+
+    /// Line and column of the only error reported for `contents`.
+    fn error_location(contents: &str) -> (String, Option<(usize, usize)>) {
+        let diagnostics = check(contents);
+        let errors: Vec<&Diagnostic> = diagnostics.iter().filter(|d| d.severity == Severity::Error).collect();
+        let [error] = errors.as_slice() else {
+            panic!("expected a single error, got {}", errors.len());
+        };
+
+        (error.message.clone(), error.location.as_ref().map(|l| (l.line, l.column)))
+    }
+
+    #[test]
+    fn unknown_fields_are_located() {
+        let (message, location) = error_location(indoc! {r#"
+            name = "app"
+            nmae = "typo"
+        "#});
+
+        assert!(message.contains("unknown field `nmae`"), "{message}");
+        assert_eq!(location, Some((2, 1)));
+    }
+
+    #[test]
+    fn unknown_main_file_fields_are_located() {
+        let (message, location) = error_location(indoc! {r#"
+            [src.main_file]
+            contents = ""
+            nmae = "app.cpp"
+        "#});
+
+        assert!(message.contains("unknown field `nmae`"), "{message}");
+        assert_eq!(location, Some((3, 1)));
+    }
+
+    #[test]
+    fn duplicates_point_at_the_second_declaration() {
+        let (message, location) = error_location(indoc! {r#"
+            [src.sources]
+            "util.hpp".contents = ""
+
+            [src.headers]
+            "util.hpp".contents = ""
+        "#});
+
+        assert_eq!(message, "file `util.hpp` is declared more than once in `src`");
+        assert_eq!(location, Some((5, 2)));
+    }
+
+    #[test]
+    fn missing_files_are_located() {
+        let (message, location) = error_location(indoc! {r#"
+            [src.sources]
+            "util.cpp".path = "does/not/exist.cpp"
+        "#});
+
+        assert_eq!(message, "source file `does/not/exist.cpp` does not exist");
+        assert_eq!(location, Some((2, 20)));
+    }
+
+    #[test]
+    fn cycles_point_at_the_closing_dependency() {
+        let (message, location) = error_location(indoc! {r#"
+            [libraries.a]
+            public_dependencies = ["b"]
+            sources."a.cpp".contents = ""
+
+            [libraries.b]
+            private_dependencies = ["a"]
+            sources."b.cpp".contents = ""
+        "#});
+
+        assert_eq!(message, "libraries depend on each other in a cycle: a -> b -> a");
+        assert_eq!(location, Some((6, 26)));
+    }
+
+    #[test]
+    fn exported_packages_are_located() {
+        let (message, location) = error_location(indoc! {r#"
+            name = "geo"
+            kind = "library"
+            install = {}
+
+            [libraries.io]
+            kind = "static"
+            private_dependencies = ["fmt"]
+            sources."io.cpp".contents = ""
+
+            [packages]
+            fmt = { git = { url = "https://github.com/fmtlib/fmt.git" } }
+        "#});
+
+        assert!(message.starts_with("installed library `io` cannot link package `fmt`"), "{message}");
+        assert_eq!(location, Some((7, 26)));
+    }
+
+    #[test]
+    fn exported_library_name_is_reserved() {
+        let (message, location) = error_location(indoc! {r#"
+            name = "geo"
+            install = {}
+
+            [libraries.lib]
+            sources."lib.cpp".contents = ""
+        "#});
+
+        assert_eq!(message, "library `lib` is reserved for the project library in the install export");
+        assert_eq!(location, Some((4, 12)));
+    }
+
+    #[test]
+    fn generated_tree_conflicts_are_reported() {
+        let (message, location) = error_location(indoc! {r#"
+            [files]
+            "esc".symlink = "elsewhere"
+            "esc/pwn".contents = "x"
+        "#});
+
+        assert_eq!(message, "`esc` is generated more than once");
+        assert_eq!(location, Some((2, 2)));
+    }
+}
@@ -0,0 +1,114 @@
+//! Renders every example and fixture description and compares the generated tree with the
+//! snapshots in `tests/snapshots`. Run with `BLESS=1` to overwrite the snapshots with the
+//! current output.
+
+use std::{env, fmt::Write as _, fs, path::PathBuf};
+
+use cpp_project_init::{
+    generators::{CmakeProject, Generator},
+    project::Project,
+    sink::{MemoryEntry, MemorySink},
+};
+
+fn manifest_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// Returns the descriptions to snapshot along with their snapshot names.
+fn descriptions() -> Vec<(String, PathBuf)> {
+    let mut descriptions = Vec::new();
+    for (group, dir) in [("examples", "examples"), ("fixtures", "tests/fixtures")] {
+        let mut entries: Vec<PathBuf> = fs::read_dir(manifest_dir().join(dir))
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        entries.sort();
+
+        for path in entries {
+            let stem = path.file_stem().unwrap().to_string_lossy();
+            descriptions.push((format!("{group}-{stem}"), path));
+        }
+    }
+
+    descriptions
+}
+
+fn render(project: Project) -> String {
+    let mut sink = MemorySink::new();
+    CmakeProject::generate_into(project, &mut sink).unwrap();
+
+    let mut sb = String::new();
+    for (path, entry) in &sink.entries {
+        let path = path.to_string_lossy().replace('\\', "/");
+        let (contents, mode) = match entry {
+            MemoryEntry::Directory => continue,
+            MemoryEntry::Symlink(target) => {
+                writeln!(sb, "=== {path} -> {}", target.to_string_lossy()).unwrap();
+                continue;
+            }
+            MemoryEntry::File { contents, mode } => (contents, mode),
+        };
+
+        match mode {
+            Some(mode) => writeln!(sb, "=== {path} (mode {mode:o})").unwrap(),
+            None => writeln!(sb, "=== {path}").unwrap(),
+        }
+        match std::str::from_utf8(contents) {
+            Ok(text) => sb.push_str(text),
+            Err(_) => writeln!(sb, "<{} bytes of binary data>", contents.len()).unwrap(),
+        }
+        if !sb.ends_with('\n') {
+            sb.push('\n');
+        }
+    }
+
+    sb
+}
+
+fn first_difference(expected: &str, actual: &str) -> String {
+    let line = expected
+        .lines()
+        .zip(actual.lines())
+        .position(|(e, a)| e != a)
+        .unwrap_or_else(|| expected.lines().count().min(actual.lines().count()));
+
+    format!(
+        "first difference at line {}:\n  expected: {:?}\n  actual:   {:?}",
+        line + 1,
+        expected.lines().nth(line).unwrap_or("<end of snapshot>"),
+        actual.lines().nth(line).unwrap_or("<end of output>"),
+    )
+}
+
+#[test]
+fn cmake_snapshots() {
+    let bless = env::var_os("BLESS").is_some();
+    let snapshots = manifest_dir().join("tests/snapshots");
+    let mut failures = Vec::new();
+
+    for (name, path) in descriptions() {
+        let actual = render(Project::load(&path).unwrap());
+        let snapshot = snapshots.join(format!("{name}.snap"));
+
+        if bless {
+            fs::write(&snapshot, &actual).unwrap();
+            continue;
+        }
+
+        match fs::read_to_string(&snapshot) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => failures.push(format!(
+                "{name}: output differs from {}\n{}",
+                snapshot.display(),
+                first_difference(&expected, &actual),
+            )),
+            Err(_) => failures.push(format!("{name}: missing snapshot {}", snapshot.display())),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{}\n\nRun with BLESS=1 to update the snapshots.",
+        failures.join("\n\n"),
+    );
+}
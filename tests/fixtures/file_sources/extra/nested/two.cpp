@@ -0,0 +1 @@
+int extra_two() { return 2; }
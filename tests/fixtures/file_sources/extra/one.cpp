@@ -0,0 +1 @@
+int extra_one() { return 1; }
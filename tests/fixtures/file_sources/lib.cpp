@@ -0,0 +1,5 @@
+#include "lib.hpp"
+
+int answer() {
+    return 42;
+}
@@ -0,0 +1,5 @@
+#include "lib.hpp"
+
+int main() {
+    return answer() == 42 ? 0 : 1;
+}
@@ -0,0 +1,167 @@
+//! Checks every example and fixture description against the JSON Schema emitted by the
+//! `schema` subcommand, so that the schema keeps accepting what the parsers accept.
+
+use std::{fs, path::PathBuf};
+
+use cpp_project_init::project::{Format, Project};
+use serde_json::Value;
+
+fn manifest_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// Parses a description into the JSON data model the schema describes.
+fn to_json(path: &PathBuf) -> Value {
+    let contents = fs::read_to_string(path).unwrap();
+    match Format::from_path(path).unwrap() {
+        Format::Json => serde_json::from_str(&contents).unwrap(),
+        Format::Toml => toml::from_str(&contents).unwrap(),
+        Format::Yaml => yaml_to_json(serde_yml::from_str(&contents).unwrap()),
+        Format::Xml => unimplemented!("no XML descriptions are checked"),
+    }
+}
+
+fn yaml_to_json(value: serde_yml::Value) -> Value {
+    match value {
+        // Tags select the variant of an enum, as single keys do in the other formats.
+        serde_yml::Value::Tagged(tagged) => {
+            let tag = tagged.tag.to_string().trim_start_matches('!').to_owned();
+            Value::Object([(tag, yaml_to_json(tagged.value))].into_iter().collect())
+        }
+        serde_yml::Value::Mapping(mapping) => mapping
+            .into_iter()
+            .map(|(key, value)| (key.as_str().unwrap().to_owned(), yaml_to_json(value)))
+            .collect(),
+        serde_yml::Value::Sequence(items) => items.into_iter().map(yaml_to_json).collect(),
+        scalar => serde_json::to_value(scalar).unwrap(),
+    }
+}
+
+/// Validates `value` against the subset of JSON Schema emitted by schemars, returning the
+/// problems found. `pattern` is not checked.
+fn validate(root: &Value, schema: &Value, value: &Value, path: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+    let Some(schema) = schema.as_object() else {
+        return errors;
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let name = reference.trim_start_matches("#/$defs/");
+        errors.extend(validate(root, &root["$defs"][name], value, path));
+    }
+
+    if let Some(types) = schema.get("type") {
+        let types: Vec<&str> = match types {
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            types => types.as_str().into_iter().collect(),
+        };
+        if !types.iter().any(|t| has_type(value, t)) {
+            errors.push(format!("{path}: expected {}", types.join(" or ")));
+        }
+    }
+
+    if let Some(expected) = schema.get("const")
+        && value != expected
+    {
+        errors.push(format!("{path}: expected {expected}"));
+    }
+
+    if let Some(Value::Array(allowed)) = schema.get("enum")
+        && !allowed.contains(value)
+    {
+        errors.push(format!("{path}: {value} is not one of {allowed:?}"));
+    }
+
+    for (keyword, exclusive) in [("anyOf", false), ("oneOf", true)] {
+        let Some(Value::Array(options)) = schema.get(keyword) else {
+            continue;
+        };
+        let matching = options
+            .iter()
+            .filter(|option| validate(root, option, value, path).is_empty())
+            .count();
+        if matching == 0 || (exclusive && matching > 1) {
+            errors.push(format!("{path}: {matching} of the `{keyword}` schemas match"));
+        }
+    }
+
+    if let Value::Object(object) = value {
+        let required = schema.get("required").and_then(Value::as_array).into_iter().flatten();
+        for key in required.filter_map(Value::as_str) {
+            if !object.contains_key(key) {
+                errors.push(format!("{path}: missing `{key}`"));
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (key, item) in object {
+            let item_path = format!("{path}.{key}");
+            match (properties.and_then(|p| p.get(key)), schema.get("additionalProperties")) {
+                (Some(property), _) => errors.extend(validate(root, property, item, &item_path)),
+                (None, Some(Value::Bool(false))) => errors.push(format!("{item_path}: unknown property")),
+                (None, Some(additional)) => errors.extend(validate(root, additional, item, &item_path)),
+                (None, None) => {}
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            errors.extend(validate(root, item_schema, item, &format!("{path}[{i}]")));
+        }
+    }
+
+    errors
+}
+
+fn has_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "null" => value.is_null(),
+        _ => false,
+    }
+}
+
+#[test]
+fn descriptions_match_the_schema() {
+    let schema = serde_json::to_value(schemars::schema_for!(Project)).unwrap();
+
+    let mut failures = Vec::new();
+    for dir in ["examples", "tests/fixtures"] {
+        let mut entries: Vec<PathBuf> = fs::read_dir(manifest_dir().join(dir))
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        entries.sort();
+
+        for path in entries {
+            let path = Project::find_description(&path).unwrap();
+            for error in validate(&schema, &schema, &to_json(&path), "") {
+                failures.push(format!("{}: {error}", path.display()));
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "descriptions do not match the schema:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn schema_rejects_unknown_fields() {
+    let schema = serde_json::to_value(schemars::schema_for!(Project)).unwrap();
+    let description: Value = toml::from_str(
+        r#"
+        [src.main_file]
+        nmae = "app.cpp"
+        contents = ""
+        "#,
+    )
+    .unwrap();
+
+    let errors = validate(&schema, &schema, &description, "");
+    assert_eq!(errors, [".src.main_file.nmae: unknown property"]);
+}